@@ -0,0 +1,151 @@
+use regex::Regex;
+
+use crate::cli::ApprovalPolicy;
+use crate::engine::Approval;
+
+/// Project file listing commands to approve, one pattern per line.
+pub const PROJECT_ALLOWLIST_PATH: &str = ".minerve/allowed_commands";
+
+/// Commands containing one of these are never matched against the allowlist,
+/// so that `cargo *` does not approve `cargo test; rm -rf ~`.
+const SHELL_OPERATORS: [&str; 8] = [";", "&", "|", "`", "$(", ">", "<", "\n"];
+
+/// `*` matches anything, everything else is literal.
+fn pattern_regex(pattern: &str) -> Regex {
+    let escaped: Vec<String> = pattern.split('*').map(regex::escape).collect();
+    Regex::new(&format!("^{}$", escaped.join(".*"))).unwrap()
+}
+
+/// Decides which shell commands run without a human in the loop.
+pub struct CommandApprover {
+    policy: ApprovalPolicy,
+    patterns: Vec<String>,
+    regexes: Vec<Regex>,
+}
+
+impl CommandApprover {
+    pub fn new(policy: ApprovalPolicy, patterns: Vec<String>) -> Self {
+        let regexes = patterns
+            .iter()
+            .map(|pattern| pattern_regex(pattern))
+            .collect();
+        Self {
+            policy,
+            patterns,
+            regexes,
+        }
+    }
+
+    /// The `--allow-command` patterns plus those of the project allowlist in
+    /// the current directory. Empty lines and `#` comments are skipped.
+    pub fn with_project_allowlist(policy: ApprovalPolicy, mut patterns: Vec<String>) -> Self {
+        let project_allowlist = std::fs::read_to_string(PROJECT_ALLOWLIST_PATH).unwrap_or_default();
+        patterns.extend(
+            project_allowlist
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(String::from),
+        );
+        Self::new(policy, patterns)
+    }
+
+    fn is_allowlisted(&self, command: &str) -> bool {
+        let command = command.trim();
+        !SHELL_OPERATORS.iter().any(|op| command.contains(op))
+            && self.regexes.iter().any(|regex| regex.is_match(command))
+    }
+
+    /// The policy's answer for `command`, or `None` when the user has to be asked.
+    pub fn decide(&self, command: &str) -> Option<Approval> {
+        match self.policy {
+            ApprovalPolicy::Always => Some(Approval::Approved),
+            ApprovalPolicy::Never => Some(Approval::Denied(format!(
+                "Command '{}' was denied: shell commands are not allowed in this run.",
+                command
+            ))),
+            _ if self.is_allowlisted(command) => Some(Approval::Approved),
+            ApprovalPolicy::Allowlist => Some(Approval::Denied(format!(
+                "Command '{}' was denied: it does not match the allowed commands ({}).",
+                command,
+                self.patterns.join(", ")
+            ))),
+            ApprovalPolicy::Ask => None,
+        }
+    }
+
+    /// Answer for a command that needs the user's approval when nobody can give it.
+    pub fn deny_unattended(&self, command: &str) -> Approval {
+        Approval::Denied(format!(
+            "Command '{}' was denied: it needs the user's approval and nobody can give it in this run.",
+            command
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approver(policy: ApprovalPolicy, patterns: &[&str]) -> CommandApprover {
+        CommandApprover::new(policy, patterns.iter().map(|p| p.to_string()).collect())
+    }
+
+    #[test]
+    fn star_matches_anything_and_the_rest_is_literal() {
+        let regex = pattern_regex("cargo *");
+        assert!(regex.is_match("cargo test"));
+        assert!(regex.is_match("cargo build --release"));
+        assert!(!regex.is_match("cargo"));
+        assert!(!regex.is_match("sudo cargo test"));
+
+        let regex = pattern_regex("ls src/*.rs");
+        assert!(regex.is_match("ls src/main.rs"));
+        assert!(!regex.is_match("ls src/mainXrs"));
+    }
+
+    #[test]
+    fn allowlisted_commands_cannot_chain_others() {
+        let approver = approver(ApprovalPolicy::Allowlist, &["cargo *"]);
+        assert!(approver.is_allowlisted("  cargo test  "));
+        for command in [
+            "cargo test; rm -rf ~",
+            "cargo test && rm -rf ~",
+            "cargo test | sh",
+            "cargo test `rm -rf ~`",
+            "cargo test $(rm -rf ~)",
+            "cargo test > ~/.bashrc",
+            "cargo test < /etc/passwd",
+            "cargo test\nrm -rf ~",
+        ] {
+            assert!(!approver.is_allowlisted(command), "{}", command);
+        }
+    }
+
+    #[test]
+    fn each_policy_decides_or_defers() {
+        let patterns = ["cargo *"];
+
+        for command in ["cargo test", "rm -rf ~"] {
+            assert_eq!(
+                approver(ApprovalPolicy::Always, &patterns).decide(command),
+                Some(Approval::Approved)
+            );
+            assert!(matches!(
+                approver(ApprovalPolicy::Never, &patterns).decide(command),
+                Some(Approval::Denied(_))
+            ));
+        }
+
+        let allowlist = approver(ApprovalPolicy::Allowlist, &patterns);
+        assert_eq!(allowlist.decide("cargo test"), Some(Approval::Approved));
+        assert!(matches!(
+            allowlist.decide("rm -rf ~"),
+            Some(Approval::Denied(reason)) if reason.contains("cargo *")
+        ));
+
+        let ask = approver(ApprovalPolicy::Ask, &patterns);
+        assert_eq!(ask.decide("cargo test"), Some(Approval::Approved));
+        assert_eq!(ask.decide("rm -rf ~"), None);
+    }
+}
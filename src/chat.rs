@@ -6,7 +6,7 @@ pub enum ChatCompletionMessageRole {
     System,
     User,
     Assistant,
-    Function,
+    Tool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,11 +16,33 @@ pub struct ChatCompletionMessage {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub function_call: Option<ChatCompletionFunctionCall>,
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_call_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub tool_calls: Option<Vec<String>>,
+    pub tool_calls: Option<Vec<ChatCompletionToolCall>>,
+}
+
+/// The `name` of the user-role messages Minerve writes itself, such as the
+/// verification reports, so that they are not mistaken for the user's prompts.
+pub const MINERVE_MESSAGE_NAME: &str = "minerve";
+
+impl ChatCompletionMessage {
+    /// Whether this is a prompt the user typed.
+    pub fn is_user_prompt(&self) -> bool {
+        matches!(self.role, ChatCompletionMessageRole::User)
+            && self.name.as_deref() != Some(MINERVE_MESSAGE_NAME)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatCompletionToolCall {
+    pub id: String,
+    #[serde(rename = "type", default = "function_type")]
+    pub kind: String,
+    pub function: ChatCompletionFunctionCall,
+}
+
+fn function_type() -> String {
+    String::from("function")
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,34 +58,87 @@ pub struct ChatCompletionFunctionDefinition {
     pub parameters: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatCompletionToolDefinition {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub function: ChatCompletionFunctionDefinition,
+}
+
+impl ChatCompletionToolDefinition {
+    pub fn function(function: ChatCompletionFunctionDefinition) -> Self {
+        Self {
+            kind: function_type(),
+            function,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatCompletionRequest {
     pub model: String,
     pub messages: Vec<ChatCompletionMessage>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub functions: Option<Vec<ChatCompletionFunctionDefinition>>,
+    pub tools: Option<Vec<ChatCompletionToolDefinition>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<ChatCompletionStreamOptions>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatCompletionStreamOptions {
+    pub include_usage: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Usage {
     pub prompt_tokens: u64,
     pub completion_tokens: u64,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatCompletionResponse {
     pub choices: Vec<ChatCompletionChoice>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub usage: Option<Usage>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatCompletionChoice {
     pub message: ChatCompletionMessage,
 }
 
-pub enum ToolCallResult {
-    Success(ChatCompletionMessage),
-    Cancelled,
-    Error(String),
+/// One server-sent event of a streamed chat completion.
+#[derive(Debug, Deserialize)]
+pub struct ChatCompletionChunk {
+    #[serde(default)]
+    pub choices: Vec<ChatCompletionChunkChoice>,
+    pub usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChatCompletionChunkChoice {
+    pub delta: ChatCompletionDelta,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ChatCompletionDelta {
+    pub content: Option<String>,
+    pub tool_calls: Option<Vec<ChatCompletionToolCallDelta>>,
+}
+
+/// Partial tool call; deltas sharing an `index` belong to the same call.
+#[derive(Debug, Deserialize)]
+pub struct ChatCompletionToolCallDelta {
+    pub index: usize,
+    pub id: Option<String>,
+    pub function: Option<ChatCompletionFunctionCallDelta>,
+}
+
+/// Partial function call; `arguments` arrives as JSON fragments to concatenate.
+#[derive(Debug, Default, Deserialize)]
+pub struct ChatCompletionFunctionCallDelta {
+    pub name: Option<String>,
+    pub arguments: Option<String>,
 }
@@ -1,8 +1,92 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::engine::AgentMode;
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Prompt string to run headlessly
     pub prompt: Option<String>,
+
+    /// Model to use, overriding MINERVE_MODEL and the provider default
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// How headless runs report their progress and result
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub output_format: OutputFormat,
+
+    #[command(flatten)]
+    pub approval: ApprovalArgs,
+
+    /// Resume the saved session with this id
+    #[arg(long, value_name = "ID", conflicts_with = "continue_session")]
+    pub resume: Option<String>,
+
+    /// Resume the last session started in the current directory
+    #[arg(long = "continue")]
+    pub continue_session: bool,
+}
+
+/// Which shell commands may run without asking; for headless runs and
+/// `mcp-serve`.
+#[derive(Args, Debug)]
+pub struct ApprovalArgs {
+    /// Which shell commands may run without asking
+    #[arg(long, value_enum, default_value_t = ApprovalPolicy::Ask)]
+    pub approve: ApprovalPolicy,
+
+    /// Pattern of commands to approve, e.g. 'cargo *'; can be repeated
+    #[arg(long = "allow-command", value_name = "PATTERN")]
+    pub allow_commands: Vec<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Report token usage and cost by day, model and project
+    Usage,
+    /// Serve the built-in tools over MCP on stdin and stdout
+    McpServe {
+        #[command(flatten)]
+        approval: ApprovalArgs,
+    },
+    /// Inspect the tools offered to the model
+    Tools {
+        #[command(subcommand)]
+        command: ToolsCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ToolsCommand {
+    /// Print the definition and JSON schema of each tool offered in a mode
+    List {
+        #[arg(long, value_enum, default_value_t = AgentMode::Normal)]
+        mode: AgentMode,
+    },
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Streamed answer on stdout, progress on stderr
+    Text,
+    /// One JSON object with the answer, tool calls, files changed, usage and exit reason
+    Json,
+    /// One JSON event per line as the turn progresses
+    StreamJson,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalPolicy {
+    /// Run allowlisted commands, ask on stdin for the others (deny them when stdin is not a terminal)
+    Ask,
+    /// Deny every command
+    Never,
+    /// Run every command
+    Always,
+    /// Run allowlisted commands, deny the others
+    Allowlist,
 }
@@ -0,0 +1,40 @@
+/// Slash commands typed into the TUI input instead of a prompt.
+pub enum TuiCommand {
+    /// `/model` shows the active model, `/model <name>` switches to another one.
+    Model(Option<String>),
+    /// `/plan <task>` asks for a plan, to review before any change is made.
+    Plan(String),
+    /// `/sessions` lists the saved sessions to switch to one of them.
+    Sessions,
+    /// `/undo` removes the last user turn and everything after it.
+    Undo,
+    /// `/fork` starts a new session from the conversation before a chosen prompt.
+    Fork,
+}
+
+/// Parses `input` as a slash command.
+///
+/// Returns `None` when the input is a regular prompt.
+pub fn parse_command(input: &str) -> Option<Result<TuiCommand, String>> {
+    let input = input.trim();
+    let command_line = input.strip_prefix('/')?;
+
+    let (name, argument) = match command_line.split_once(char::is_whitespace) {
+        Some((name, rest)) => (name, Some(rest.trim().to_string())),
+        None => (command_line, None),
+    };
+    let argument = argument.filter(|argument| !argument.is_empty());
+
+    let command = match name {
+        "model" => Ok(TuiCommand::Model(argument)),
+        "plan" => argument
+            .map(TuiCommand::Plan)
+            .ok_or_else(|| String::from("Usage: /plan <task>")),
+        "sessions" => Ok(TuiCommand::Sessions),
+        "undo" => Ok(TuiCommand::Undo),
+        "fork" => Ok(TuiCommand::Fork),
+        _ => Err(format!("Unknown command: /{}", name)),
+    };
+
+    Some(command)
+}
@@ -0,0 +1,166 @@
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::engine::AgentMode;
+use crate::error::MinerveError;
+use crate::hooks::HooksConfig;
+
+/// Project settings, relative to the directory Minerve runs in.
+pub const PROJECT_CONFIG_PATH: &str = ".minerve/config.json";
+/// User settings, relative to the home directory.
+pub const USER_CONFIG_PATH: &str = ".minerve/config.json";
+pub const DEFAULT_MAX_VERIFY_ATTEMPTS: usize = 3;
+
+fn default_max_verify_attempts() -> usize {
+    DEFAULT_MAX_VERIFY_ATTEMPTS
+}
+
+/// Command that checks the project after the model changed files, e.g.
+/// `cargo check` or `yarn tsc`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct VerifyConfig {
+    pub command: String,
+    /// How many failed checks are sent back to the model before giving up.
+    #[serde(default = "default_max_verify_attempts")]
+    pub max_attempts: usize,
+}
+
+/// Tools to offer or to leave out, by name.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ToolSelection {
+    pub enabled: Vec<String>,
+    pub disabled: Vec<String>,
+}
+
+impl ToolSelection {
+    /// Whether to keep the tool `name`, `default` when not listed.
+    pub fn keeps(&self, name: &str, default: bool) -> bool {
+        if self.disabled.iter().any(|disabled| disabled == name) {
+            false
+        } else {
+            default || self.enabled.iter().any(|enabled| enabled == name)
+        }
+    }
+
+    /// `self` with `later` applied on top, so that `later` wins when both
+    /// list a tool.
+    fn then(self, later: ToolSelection) -> ToolSelection {
+        let mut enabled: Vec<String> = self
+            .enabled
+            .into_iter()
+            .filter(|name| !later.disabled.contains(name))
+            .collect();
+        enabled.extend(later.enabled.iter().cloned());
+        let mut disabled: Vec<String> = self
+            .disabled
+            .into_iter()
+            .filter(|name| !later.enabled.contains(name))
+            .collect();
+        disabled.extend(later.disabled);
+
+        ToolSelection { enabled, disabled }
+    }
+}
+
+/// Which tools the model is offered, overall and in each mode.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ToolsConfig {
+    #[serde(flatten)]
+    pub selection: ToolSelection,
+    /// Changes to the tools of one mode, e.g. `planning`.
+    pub modes: HashMap<AgentMode, ToolSelection>,
+}
+
+impl ToolsConfig {
+    /// Project settings win over the user's.
+    pub fn merged(user: ToolsConfig, project: ToolsConfig) -> ToolsConfig {
+        let mut modes = user.modes;
+        for (mode, selection) in project.modes {
+            let merged = modes.remove(&mode).unwrap_or_default().then(selection);
+            modes.insert(mode, merged);
+        }
+
+        ToolsConfig {
+            selection: user.selection.then(project.selection),
+            modes,
+        }
+    }
+}
+
+/// An MCP server, started over stdio for the whole session.
+#[derive(Debug, Clone, Deserialize)]
+pub struct McpServerConfig {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Variables added to the server's environment.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+}
+
+/// Settings from `~/.minerve/config.json` and the project's `.minerve/config.json`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub verify: Option<VerifyConfig>,
+    pub hooks: HooksConfig,
+    pub tools: ToolsConfig,
+    /// MCP servers whose tools are offered with the built-in ones, by name.
+    pub mcp_servers: BTreeMap<String, McpServerConfig>,
+}
+
+/// Reads one config file; a missing file gives the defaults.
+fn read_config(path: &Path) -> Result<Config, MinerveError> {
+    let json = match std::fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(err) => {
+            return Err(MinerveError::io(
+                format!("Could not read {}", path.display()),
+                err,
+            ))
+        }
+    };
+    serde_json::from_str(&json)
+        .map_err(|err| MinerveError::json(format!("Could not parse {}", path.display()), err))
+}
+
+impl Config {
+    /// Reads the user config, then the project config. Project settings win,
+    /// hooks from both run, the user's first, and MCP servers from both are
+    /// started, the project's replacing the user's of the same name.
+    pub fn load() -> Result<Self, MinerveError> {
+        let user_path = dirs::home_dir().map(|home| home.join(USER_CONFIG_PATH));
+        let project_path = PathBuf::from(PROJECT_CONFIG_PATH);
+
+        let user = match &user_path {
+            Some(path) => read_config(path)?,
+            None => Config::default(),
+        };
+        // Running from the home directory, both are the same file
+        let same_file = match (&user_path, project_path.canonicalize()) {
+            (Some(user_path), Ok(project)) => {
+                user_path.canonicalize().is_ok_and(|user| user == project)
+            }
+            _ => false,
+        };
+        if same_file {
+            return Ok(user);
+        }
+        let project = read_config(&project_path)?;
+
+        Ok(Config {
+            verify: project.verify.or(user.verify),
+            hooks: HooksConfig::merged(user.hooks, project.hooks),
+            tools: ToolsConfig::merged(user.tools, project.tools),
+            mcp_servers: user
+                .mcp_servers
+                .into_iter()
+                .chain(project.mcp_servers)
+                .collect(),
+        })
+    }
+}
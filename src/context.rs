@@ -0,0 +1,236 @@
+use std::env;
+use std::sync::Arc;
+
+use crate::chat::{ChatCompletionMessage, ChatCompletionMessageRole, ChatCompletionRequest};
+use crate::engine::post_request_with_token_count;
+use crate::providers::LlmProvider;
+use crate::token_counter::TokenCounter;
+
+pub const DEFAULT_CONTEXT_WINDOW: usize = 128_000;
+
+/// Rough average for English text and code; good enough to stay under the window.
+const CHARS_PER_TOKEN: usize = 4;
+/// Role and framing tokens added by the API around each message.
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+/// Longest excerpt of a single message included in a summarization transcript.
+const TRANSCRIPT_MESSAGE_CHARS: usize = 2000;
+
+const SUMMARY_PROMPT: &str = "Summarize the following conversation between a user and a coding assistant. Keep the user's goals, decisions taken, files read or changed, commands run and anything still left to do. Be concise.";
+
+/// Keeps the conversation under the model's context window.
+///
+/// Oversized tool results are truncated, and when the history still does not
+/// fit, older turns are replaced by an LLM-written summary.
+pub struct ContextManager {
+    window_tokens: usize,
+}
+
+impl ContextManager {
+    pub fn new(window_tokens: usize) -> Self {
+        Self { window_tokens }
+    }
+
+    /// Reads the window size in tokens from `MINERVE_CONTEXT_WINDOW`.
+    pub fn from_env() -> Self {
+        let window_tokens = env::var("MINERVE_CONTEXT_WINDOW")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_CONTEXT_WINDOW);
+        Self::new(window_tokens)
+    }
+
+    pub fn estimate_tokens(message: &ChatCompletionMessage) -> usize {
+        let content_len = message.content.as_ref().map(|c| c.len()).unwrap_or(0);
+        let tool_calls_len: usize = message
+            .tool_calls
+            .iter()
+            .flatten()
+            .map(|tool_call| tool_call.function.name.len() + tool_call.function.arguments.len())
+            .sum();
+
+        (content_len + tool_calls_len).div_ceil(CHARS_PER_TOKEN) + MESSAGE_OVERHEAD_TOKENS
+    }
+
+    pub fn total_tokens(messages: &[ChatCompletionMessage]) -> usize {
+        messages.iter().map(Self::estimate_tokens).sum()
+    }
+
+    /// Tokens available to the history; the rest is left for tool schemas and the answer.
+    fn budget(&self) -> usize {
+        self.window_tokens * 3 / 4
+    }
+
+    pub fn needs_summary(&self, history: &[ChatCompletionMessage]) -> bool {
+        Self::total_tokens(history) > self.budget()
+    }
+
+    /// Fits `history` into the budget, returning whether it was changed.
+    pub async fn fit(
+        &self,
+        history: &mut Vec<ChatCompletionMessage>,
+        provider: &dyn LlmProvider,
+        model: &str,
+        token_counter: Arc<TokenCounter>,
+    ) -> bool {
+        let changed = self.truncate_oversized_tool_results(history);
+
+        if !self.needs_summary(history) {
+            return changed;
+        }
+
+        let Some((start, end)) = self.summary_range(history) else {
+            return changed;
+        };
+
+        let transcript = transcript(&history[start..end]);
+        let request = ChatCompletionRequest {
+            model: model.to_string(),
+            messages: vec![
+                ChatCompletionMessage {
+                    role: ChatCompletionMessageRole::System,
+                    content: Some(String::from(SUMMARY_PROMPT)),
+                    name: None,
+                    tool_call_id: None,
+                    tool_calls: None,
+                },
+                ChatCompletionMessage {
+                    role: ChatCompletionMessageRole::User,
+                    content: Some(transcript),
+                    name: None,
+                    tool_call_id: None,
+                    tool_calls: None,
+                },
+            ],
+            tools: None,
+            stream: None,
+            stream_options: None,
+        };
+
+        let summary = post_request_with_token_count(provider, request, None, token_counter)
+            .await
+            .ok()
+            .and_then(|response| response.choices.into_iter().next())
+            .and_then(|choice| choice.message.content);
+
+        match summary {
+            Some(summary) => {
+                history.splice(
+                    start..end,
+                    [ChatCompletionMessage {
+                        role: ChatCompletionMessageRole::System,
+                        content: Some(format!("Summary of the earlier conversation:\n{}", summary)),
+                        name: None,
+                        tool_call_id: None,
+                        tool_calls: None,
+                    }],
+                );
+            }
+            None => {
+                // Summarizing failed; at least drop the bulk of the old tool output
+                for message in history[start..end].iter_mut() {
+                    if let ChatCompletionMessageRole::Tool = message.role {
+                        message.content = Some(String::from("[cleaned from history]"));
+                    }
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Caps each tool result at a quarter of the budget.
+    fn truncate_oversized_tool_results(&self, history: &mut [ChatCompletionMessage]) -> bool {
+        let max_chars = self.budget() / 4 * CHARS_PER_TOKEN;
+        let mut changed = false;
+
+        for message in history.iter_mut() {
+            if let ChatCompletionMessageRole::Tool = message.role {
+                if let Some(content) = &message.content {
+                    if content.len() > max_chars {
+                        message.content = Some(format!(
+                            "{}\n...[truncated to fit the context window]",
+                            clip(content, max_chars)
+                        ));
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        changed
+    }
+
+    /// Range of older messages to summarize: everything after the leading system
+    /// prompt up to the most recent messages that fit in half the budget.
+    fn summary_range(&self, history: &[ChatCompletionMessage]) -> Option<(usize, usize)> {
+        let is_user =
+            |index: &usize| matches!(history[*index].role, ChatCompletionMessageRole::User);
+
+        let start = history
+            .iter()
+            .take_while(|msg| matches!(msg.role, ChatCompletionMessageRole::System))
+            .count();
+
+        let mut kept_tokens = 0;
+        let mut keep_from = history.len();
+        for index in (start..history.len()).rev() {
+            kept_tokens += Self::estimate_tokens(&history[index]);
+            if kept_tokens > self.budget() / 2 {
+                break;
+            }
+            keep_from = index;
+        }
+
+        // Cut on a user message so that tool calls stay with their results. When the
+        // kept messages hold none, keep the whole current turn instead.
+        let end = (keep_from..history.len())
+            .find(is_user)
+            .or_else(|| (start..keep_from).rev().find(is_user))?;
+
+        if end > start {
+            Some((start, end))
+        } else {
+            None
+        }
+    }
+}
+
+/// Cuts `content` to at most `max_len` bytes on a char boundary.
+fn clip(content: &str, max_len: usize) -> &str {
+    let mut end = content.len().min(max_len);
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    &content[..end]
+}
+
+fn transcript(messages: &[ChatCompletionMessage]) -> String {
+    let mut lines = Vec::new();
+
+    for message in messages {
+        let role = match message.role {
+            ChatCompletionMessageRole::System => "System",
+            ChatCompletionMessageRole::User => "User",
+            ChatCompletionMessageRole::Assistant => "Assistant",
+            ChatCompletionMessageRole::Tool => "Tool result",
+        };
+
+        if let Some(content) = message.content.as_ref().filter(|c| !c.is_empty()) {
+            let excerpt = clip(content, TRANSCRIPT_MESSAGE_CHARS);
+            if excerpt.len() < content.len() {
+                lines.push(format!("{}: {}\n...[truncated]", role, excerpt));
+            } else {
+                lines.push(format!("{}: {}", role, content));
+            }
+        }
+
+        for tool_call in message.tool_calls.iter().flatten() {
+            lines.push(format!(
+                "Assistant called {}({})",
+                tool_call.function.name, tool_call.function.arguments
+            ));
+        }
+    }
+
+    lines.join("\n\n")
+}
@@ -0,0 +1,773 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::chat::{
+    ChatCompletionFunctionDefinition, ChatCompletionMessage, ChatCompletionMessageRole,
+    ChatCompletionRequest, ChatCompletionResponse, ChatCompletionStreamOptions,
+    ChatCompletionToolCall, ChatCompletionToolDefinition, MINERVE_MESSAGE_NAME,
+};
+use crate::config::Config;
+use crate::context::ContextManager;
+use crate::error::MinerveError;
+use crate::hooks::HooksConfig;
+use crate::limits::{TurnLimit, TurnLimits};
+use crate::plan::Plan;
+use crate::providers::LlmProvider;
+use crate::token_counter::TokenCounter;
+use crate::tools::registry::ToolRegistry;
+use crate::tools::utils::{run_process, truncate};
+use crate::tools::{parameters_schema, Param, ParamType, Tool, ToolOutput, ToolParams};
+
+/// Tool handled by the engine itself to track progress on an accepted plan.
+const COMPLETE_PLAN_STEP: &str = "complete_plan_step";
+/// Tool handled by the engine itself to run a sub-agent.
+const DELEGATE_TASK: &str = "delegate_task";
+
+const DELEGATE_PROMPT: &str = "You are a sub-agent working for another coding assistant. Complete the task below with the read-only tools available. Then answer with a concise summary of what you found: relevant files, functions and facts. Your final answer is the only thing the other assistant will see.";
+
+/// Verification output beyond this is cut before it is sent to the model.
+const MAX_VERIFY_OUTPUT_LEN: usize = 10_000;
+
+pub const MAX_REQUEST_RETRIES: u32 = 5;
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// What happens during a turn, in order, as seen by a front end.
+pub enum AgentEvent {
+    /// Assistant text as it streams in.
+    TextDelta(String),
+    /// A complete assistant message, now part of the history.
+    AssistantMessage(ChatCompletionMessage),
+    /// Progress worth showing in a status line, e.g. summarization.
+    Status(String),
+    /// A request failed and is retried after `delay`.
+    Retrying {
+        reason: String,
+        attempt: u32,
+        delay: Duration,
+    },
+    ToolStarted(ChatCompletionToolCall),
+    /// The shell command needs the user's approval before it runs.
+    /// Dropping `respond` counts as a refusal.
+    ApprovalRequested {
+        command: String,
+        respond: oneshot::Sender<Approval>,
+    },
+    ToolFinished {
+        call: ChatCompletionToolCall,
+        output: Result<ToolOutput, MinerveError>,
+    },
+    Usage {
+        prompt_tokens: u64,
+        completion_tokens: u64,
+    },
+    /// The verification command ran after the model changed files.
+    VerificationFinished {
+        command: String,
+        passed: bool,
+        output: String,
+    },
+    /// A step of the plan being executed was completed.
+    PlanUpdated(Plan),
+    /// The turn stopped on an error.
+    Error(MinerveError),
+    Done(StopReason),
+}
+
+/// The answer to an `ApprovalRequested` event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Approval {
+    Approved,
+    /// The user said no; the turn stops.
+    Refused,
+    /// An approval policy said no; the model is told why and carries on.
+    Denied(String),
+}
+
+/// Which tools the model may use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum AgentMode {
+    /// Every tool is available.
+    Normal,
+    /// Only read-only tools, until the user accepts a plan.
+    Planning,
+    /// Every tool, plus `complete_plan_step` to report progress on the plan.
+    Executing,
+    /// A sub-agent started by `delegate_task`: read-only tools only.
+    Delegated,
+}
+
+impl AgentMode {
+    /// The default for `tool`, before the config's changes to the mode.
+    pub fn allows(&self, tool: &dyn Tool) -> bool {
+        match self {
+            AgentMode::Planning | AgentMode::Delegated => tool.is_read_only(),
+            AgentMode::Normal | AgentMode::Executing => true,
+        }
+    }
+}
+
+/// Why a turn ended.
+#[derive(Debug, Clone, Copy)]
+pub enum StopReason {
+    /// The model answered without calling tools, or the user declined a tool.
+    Completed,
+    /// A turn limit was reached while the model was still calling tools.
+    LimitReached(TurnLimit),
+    /// A request failed after its retries.
+    Failed,
+}
+
+impl StopReason {
+    /// Process exit status for headless runs; limits get their own status so
+    /// that scripts can tell an unfinished task from a failure.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            StopReason::Completed => 0,
+            StopReason::Failed => 1,
+            StopReason::LimitReached(_) => 3,
+        }
+    }
+
+    /// Stable name of the reason, for machine-readable output.
+    pub fn name(&self) -> &'static str {
+        match self {
+            StopReason::Completed => "completed",
+            StopReason::Failed => "failed",
+            StopReason::LimitReached(_) => "limit_reached",
+        }
+    }
+}
+
+pub type EventSender = mpsc::UnboundedSender<AgentEvent>;
+pub type EventReceiver = mpsc::UnboundedReceiver<AgentEvent>;
+
+pub fn event_channel() -> (EventSender, EventReceiver) {
+    mpsc::unbounded_channel()
+}
+
+/// Sends the request, retrying transient failures with exponential backoff.
+///
+/// Streamed text, retries and usage are reported on `events` when given. An
+/// attempt that already streamed text is not retried, as the text would be
+/// shown twice.
+pub async fn post_request_with_token_count(
+    provider: &dyn LlmProvider,
+    request: ChatCompletionRequest,
+    events: Option<&EventSender>,
+    token_counter: Arc<TokenCounter>,
+) -> Result<ChatCompletionResponse, MinerveError> {
+    let streamed = AtomicBool::new(false);
+    let mut on_delta = |delta: String| {
+        streamed.store(true, Ordering::Relaxed);
+        if let Some(events) = events {
+            let _ = events.send(AgentEvent::TextDelta(delta));
+        }
+    };
+
+    let mut attempt = 0;
+    let chat_response = loop {
+        match provider.send(request.clone(), &mut on_delta).await {
+            Ok(chat_response) => break chat_response,
+            Err(err)
+                if err.is_retryable()
+                    && attempt < MAX_REQUEST_RETRIES
+                    && !streamed.load(Ordering::Relaxed) =>
+            {
+                attempt += 1;
+                let backoff = INITIAL_RETRY_DELAY * 2u32.pow(attempt - 1);
+                let delay = err.retry_after().unwrap_or(backoff).min(MAX_RETRY_DELAY);
+                if let Some(events) = events {
+                    let _ = events.send(AgentEvent::Retrying {
+                        reason: err.to_string(),
+                        attempt,
+                        delay,
+                    });
+                }
+                tokio::time::sleep(delay).await;
+            }
+            Err(err) => return Err(err),
+        }
+    };
+
+    if let Some(ref usage) = chat_response.usage {
+        token_counter.record_usage(&request.model, usage.prompt_tokens, usage.completion_tokens);
+        if let Some(events) = events {
+            let _ = events.send(AgentEvent::Usage {
+                prompt_tokens: usage.prompt_tokens,
+                completion_tokens: usage.completion_tokens,
+            });
+        }
+    }
+
+    Ok(chat_response)
+}
+
+/// Takes the assistant message of the first choice of a response.
+fn first_message(response: ChatCompletionResponse) -> Result<ChatCompletionMessage, MinerveError> {
+    response
+        .choices
+        .into_iter()
+        .next()
+        .map(|choice| choice.message)
+        .ok_or(MinerveError::EmptyResponse)
+}
+
+/// Builds the `tool` message answering the tool call with the given id.
+pub fn tool_response_message(
+    tool_call: &ChatCompletionToolCall,
+    content: String,
+) -> ChatCompletionMessage {
+    ChatCompletionMessage {
+        role: ChatCompletionMessageRole::Tool,
+        content: Some(content),
+        name: Some(tool_call.function.name.clone()),
+        tool_call_id: Some(tool_call.id.clone()),
+        tool_calls: None,
+    }
+}
+
+fn delegate_task_parameters() -> Vec<Param> {
+    vec![Param::new(
+        "task",
+        ParamType::String,
+        "What the sub-agent should find out, with any context it needs.",
+    )]
+}
+
+fn complete_plan_step_parameters() -> Vec<Param> {
+    vec![Param::new(
+        "step",
+        ParamType::Integer,
+        "Number of the finished step, starting at 1.",
+    )]
+}
+
+/// Arguments of `tool_call`, checked against `params`.
+fn tool_params(
+    tool_call: &ChatCompletionToolCall,
+    params: &[Param],
+) -> Result<ToolParams, MinerveError> {
+    let args_str = &tool_call.function.arguments;
+    let arguments = match serde_json::from_str::<serde_json::Value>(args_str) {
+        Ok(serde_json::Value::Object(map)) => map,
+        // Some models send no arguments at all for tools without parameters
+        _ if args_str.trim().is_empty() => serde_json::Map::new(),
+        _ => {
+            return Err(MinerveError::ToolArgument(format!(
+                "Arguments must be a JSON object, got: {}",
+                args_str
+            )))
+        }
+    };
+    ToolParams::parse(arguments, params)
+}
+
+/// The tools offered to the model in `mode`.
+pub fn tool_definitions(
+    tools: &ToolRegistry,
+    mode: AgentMode,
+) -> Vec<ChatCompletionToolDefinition> {
+    let mut definitions: Vec<ChatCompletionToolDefinition> = tools
+        .tools(mode)
+        .map(|tool| {
+            ChatCompletionToolDefinition::function(ChatCompletionFunctionDefinition {
+                name: tool.name().to_string(),
+                description: Some(tool.description().to_string()),
+                parameters: Some(tool.function_definition()),
+            })
+        })
+        .collect();
+
+    if mode != AgentMode::Delegated {
+        definitions.push(ChatCompletionToolDefinition::function(
+            ChatCompletionFunctionDefinition {
+                name: DELEGATE_TASK.to_string(),
+                description: Some(String::from(
+                    "Delegates an exploration task to a sub-agent with its own context and read-only tools. Only its summary comes back, which keeps search results and file contents out of this conversation.",
+                )),
+                parameters: Some(parameters_schema(&delegate_task_parameters())),
+            },
+        ));
+    }
+
+    if mode == AgentMode::Executing {
+        definitions.push(ChatCompletionToolDefinition::function(
+            ChatCompletionFunctionDefinition {
+                name: COMPLETE_PLAN_STEP.to_string(),
+                description: Some(String::from(
+                    "Marks a step of the accepted plan as done. Call it after finishing each step.",
+                )),
+                parameters: Some(parameters_schema(&complete_plan_step_parameters())),
+            },
+        ));
+    }
+
+    definitions
+}
+
+/// Runs one tool call between its pre- and post-tool hooks, asking for
+/// approval on `events` before shell commands.
+pub async fn handle_tool_call(
+    tool_call: &ChatCompletionToolCall,
+    tools: &ToolRegistry,
+    mode: AgentMode,
+    hooks: &HooksConfig,
+    events: &EventSender,
+) -> Result<ToolOutput, MinerveError> {
+    let tool_name = &tool_call.function.name;
+
+    let Some(tool) = tools.get(tool_name.as_str()) else {
+        return Err(MinerveError::UnknownTool(tool_name.clone()));
+    };
+
+    if !tools.allows(mode, tool.as_ref()) {
+        let reason = match mode {
+            AgentMode::Planning => "until the user accepts the plan",
+            _ => "to sub-agents",
+        };
+        return Err(MinerveError::ToolUnavailable(format!(
+            "{} is not available {}",
+            tool_name, reason
+        )));
+    }
+
+    let params = tool_params(tool_call, &tool.parameters())?;
+    let arguments = params.to_json();
+
+    hooks.run_pre_tool(tool_name, &arguments).await?;
+
+    if tool_name.as_str() == "run_shell_command" {
+        let command = params.get_optional_string("command")?.unwrap_or_default();
+        let (respond, approval) = oneshot::channel();
+        let _ = events.send(AgentEvent::ApprovalRequested { command, respond });
+
+        // Awaiting keeps the task cancellable while the user decides
+        match approval.await.unwrap_or(Approval::Refused) {
+            Approval::Approved => {}
+            Approval::Refused => {
+                return Err(MinerveError::Permission(String::from(
+                    "Command execution cancelled by user.",
+                )))
+            }
+            Approval::Denied(reason) => return Err(MinerveError::Denied(reason)),
+        }
+    }
+
+    let output = tool.run(params).await;
+
+    for command in hooks.run_post_tool(tool_name, &arguments, &output).await {
+        let _ = events.send(AgentEvent::Status(format!(
+            "post-tool hook failed: {}",
+            command
+        )));
+    }
+    output
+}
+
+/// Runs the agent loop: sends the conversation, runs the requested tools and
+/// repeats until the model answers without tool calls.
+///
+/// Front ends only consume the events; the TUI, the headless runner and tests
+/// share this single loop.
+pub struct AgentEngine {
+    provider: Arc<dyn LlmProvider>,
+    model: Mutex<String>,
+    token_counter: Arc<TokenCounter>,
+    context_manager: ContextManager,
+    limits: TurnLimits,
+    config: Config,
+    tools: Arc<ToolRegistry>,
+    mode: Mutex<AgentMode>,
+    plan: Mutex<Option<Plan>>,
+}
+
+impl AgentEngine {
+    pub fn new(
+        provider: Arc<dyn LlmProvider>,
+        model: String,
+        token_counter: Arc<TokenCounter>,
+        context_manager: ContextManager,
+        limits: TurnLimits,
+        config: Config,
+        tools: Arc<ToolRegistry>,
+    ) -> Self {
+        Self {
+            provider,
+            model: Mutex::new(model),
+            token_counter,
+            context_manager,
+            limits,
+            config,
+            tools,
+            mode: Mutex::new(AgentMode::Normal),
+            plan: Mutex::new(None),
+        }
+    }
+
+    pub fn mode(&self) -> AgentMode {
+        *self.mode.lock().unwrap()
+    }
+
+    pub fn set_mode(&self, mode: AgentMode) {
+        *self.mode.lock().unwrap() = mode;
+    }
+
+    pub fn plan(&self) -> Option<Plan> {
+        self.plan.lock().unwrap().clone()
+    }
+
+    pub fn set_plan(&self, plan: Option<Plan>) {
+        *self.plan.lock().unwrap() = plan;
+    }
+
+    /// Runs `task` in a read-only sub-agent with its own history and returns
+    /// its final answer. Its usage is recorded in this engine's token counter
+    /// and added to `turn_tokens`, so that it counts against the turn limit.
+    /// The sub-agent gets what is left of the turn's limits, `budget`.
+    async fn delegate_task(
+        &self,
+        tool_call: &ChatCompletionToolCall,
+        events: &EventSender,
+        turn_tokens: &mut u64,
+        budget: TurnLimits,
+    ) -> Result<ToolOutput, MinerveError> {
+        let task = tool_params(tool_call, &delegate_task_parameters())?.get_string("task")?;
+
+        let child = AgentEngine::new(
+            self.provider.clone(),
+            self.model(),
+            self.token_counter.clone(),
+            ContextManager::from_env(),
+            budget,
+            // Read-only tools change nothing to verify; hooks still apply
+            Config {
+                verify: None,
+                ..self.config.clone()
+            },
+            self.tools.clone(),
+        );
+        child.set_mode(AgentMode::Delegated);
+
+        let messages = Arc::new(Mutex::new(vec![
+            ChatCompletionMessage {
+                role: ChatCompletionMessageRole::System,
+                content: Some(String::from(DELEGATE_PROMPT)),
+                name: None,
+                tool_call_id: None,
+                tool_calls: None,
+            },
+            ChatCompletionMessage {
+                role: ChatCompletionMessageRole::User,
+                content: Some(task),
+                name: None,
+                tool_call_id: None,
+                tool_calls: None,
+            },
+        ]));
+
+        // Only progress that matters to the user reaches the parent; the
+        // sub-agent's text and tool calls stay out of the conversation
+        let (child_events, mut child_events_rx) = event_channel();
+        let mut child_tokens = 0;
+        let forward = async {
+            while let Some(event) = child_events_rx.recv().await {
+                match event {
+                    AgentEvent::Usage {
+                        prompt_tokens,
+                        completion_tokens,
+                    } => {
+                        child_tokens += prompt_tokens + completion_tokens;
+                        let _ = events.send(event);
+                    }
+                    AgentEvent::Status(_) | AgentEvent::Retrying { .. } => {
+                        let _ = events.send(event);
+                    }
+                    AgentEvent::ToolStarted(call) => {
+                        let _ = events.send(AgentEvent::Status(format!(
+                            "sub-agent running tool: {}",
+                            call.function.name
+                        )));
+                    }
+                    _ => {}
+                }
+            }
+        };
+        let (reason, _) =
+            tokio::join!(Box::pin(child.run(messages.clone(), child_events)), forward);
+        *turn_tokens += child_tokens;
+
+        let summary = messages
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|msg| matches!(msg.role, ChatCompletionMessageRole::Assistant))
+            .and_then(|msg| msg.content.clone())
+            .unwrap_or_default();
+
+        match reason {
+            StopReason::Completed => Ok(ToolOutput::success(summary)),
+            StopReason::LimitReached(limit) => Ok(ToolOutput::success(format!(
+                "The sub-agent stopped after reaching the {}. Its last answer:\n{}",
+                limit, summary
+            ))),
+            StopReason::Failed => Ok(ToolOutput::error(
+                "The sub-agent failed before finishing the task.",
+            )),
+        }
+    }
+
+    fn complete_plan_step(
+        &self,
+        tool_call: &ChatCompletionToolCall,
+        events: &EventSender,
+    ) -> Result<ToolOutput, MinerveError> {
+        let step = tool_params(tool_call, &complete_plan_step_parameters())?.get_integer("step")?;
+
+        let mut plan = self.plan.lock().unwrap();
+        let plan = plan
+            .as_mut()
+            .ok_or_else(|| MinerveError::ToolUnavailable(String::from("There is no plan.")))?;
+
+        if !plan.complete_step(step as usize) {
+            return Err(MinerveError::ToolArgument(format!(
+                "The plan has no step {}.",
+                step
+            )));
+        }
+        let _ = events.send(AgentEvent::PlanUpdated(plan.clone()));
+
+        if plan.is_complete() {
+            Ok(ToolOutput::success("All steps of the plan are done."))
+        } else {
+            Ok(ToolOutput::success(format!(
+                "Step {} marked as done.",
+                step
+            )))
+        }
+    }
+
+    pub fn model(&self) -> String {
+        self.model.lock().unwrap().clone()
+    }
+
+    pub fn set_model(&self, model: String) {
+        *self.model.lock().unwrap() = model;
+    }
+
+    pub fn token_counter(&self) -> Arc<TokenCounter> {
+        self.token_counter.clone()
+    }
+
+    /// Runs the verification command once the model is done with a turn that
+    /// changed files. Returns the report to send back to the model when the
+    /// check fails and it has attempts left.
+    async fn verify_edits(&self, attempts: &mut usize, events: &EventSender) -> Option<String> {
+        let verify = self.config.verify.as_ref()?;
+        let _ = events.send(AgentEvent::Status(format!("verifying: {}", verify.command)));
+
+        let (passed, output) = match run_process("sh", &["-c", &verify.command]).await {
+            Ok(out) => (
+                out.status.success(),
+                format!(
+                    "{}{}",
+                    String::from_utf8_lossy(&out.stdout),
+                    String::from_utf8_lossy(&out.stderr)
+                ),
+            ),
+            Err(err) => (
+                false,
+                MinerveError::io("Failed to run command", err).to_string(),
+            ),
+        };
+        let output = truncate(output, MAX_VERIFY_OUTPUT_LEN);
+        let _ = events.send(AgentEvent::VerificationFinished {
+            command: verify.command.clone(),
+            passed,
+            output: output.clone(),
+        });
+
+        if passed || *attempts >= verify.max_attempts {
+            return None;
+        }
+        *attempts += 1;
+        Some(format!(
+            "The verification command `{}` failed after your changes (attempt {}/{}). Fix the problems it reports:\n\n{}",
+            verify.command, attempts, verify.max_attempts, output
+        ))
+    }
+
+    /// Runs one user turn on `messages`, which already end with the user's
+    /// message, or continues a turn stopped by a limit. Every message is pushed
+    /// as soon as it exists, so the history stays valid if the task is aborted.
+    pub async fn run(
+        &self,
+        messages: Arc<Mutex<Vec<ChatCompletionMessage>>>,
+        events: EventSender,
+    ) -> StopReason {
+        let mode = self.mode();
+        let tools = tool_definitions(&self.tools, mode);
+        let started = Instant::now();
+        let mut iterations = 0;
+        let mut turn_tokens = 0;
+        let mut files_changed = false;
+        let mut verify_attempts = 0;
+
+        let reason = loop {
+            if let Some(limit) = self
+                .limits
+                .exceeded(iterations, started.elapsed(), turn_tokens)
+            {
+                break StopReason::LimitReached(limit);
+            }
+
+            // Keep the history under the context window
+            let mut history = messages.lock().unwrap().clone();
+            if self.context_manager.needs_summary(&history) {
+                let _ = events.send(AgentEvent::Status(String::from(
+                    "summarizing earlier conversation...",
+                )));
+            }
+            let history_changed = self
+                .context_manager
+                .fit(
+                    &mut history,
+                    self.provider.as_ref(),
+                    &self.model(),
+                    self.token_counter.clone(),
+                )
+                .await;
+            if history_changed {
+                *messages.lock().unwrap() = history.clone();
+            }
+
+            let request = ChatCompletionRequest {
+                model: self.model(),
+                messages: history,
+                tools: if tools.is_empty() {
+                    None
+                } else {
+                    Some(tools.clone())
+                },
+                stream: Some(true),
+                stream_options: Some(ChatCompletionStreamOptions {
+                    include_usage: true,
+                }),
+            };
+
+            let chat_result = post_request_with_token_count(
+                self.provider.as_ref(),
+                request,
+                Some(&events),
+                self.token_counter.clone(),
+            )
+            .await
+            .and_then(|response| {
+                if let Some(usage) = &response.usage {
+                    turn_tokens += usage.prompt_tokens + usage.completion_tokens;
+                }
+                first_message(response)
+            });
+            iterations += 1;
+
+            let assistant_message = match chat_result {
+                Ok(assistant_message) => assistant_message,
+                Err(err) => {
+                    let _ = events.send(AgentEvent::Error(err));
+                    break StopReason::Failed;
+                }
+            };
+
+            // The history must keep the tool calls so that they can be answered by id
+            messages.lock().unwrap().push(assistant_message.clone());
+            let _ = events.send(AgentEvent::AssistantMessage(assistant_message.clone()));
+
+            let Some(tool_calls) = assistant_message
+                .tool_calls
+                .filter(|calls| !calls.is_empty())
+            else {
+                if files_changed {
+                    files_changed = false;
+                    if let Some(report) = self.verify_edits(&mut verify_attempts, &events).await {
+                        messages.lock().unwrap().push(ChatCompletionMessage {
+                            role: ChatCompletionMessageRole::User,
+                            content: Some(report),
+                            name: Some(String::from(MINERVE_MESSAGE_NAME)),
+                            tool_call_id: None,
+                            tool_calls: None,
+                        });
+                        continue;
+                    }
+                }
+                break StopReason::Completed;
+            };
+
+            let mut stopped = false;
+            for tool_call in tool_calls {
+                if stopped {
+                    messages.lock().unwrap().push(tool_response_message(
+                        &tool_call,
+                        String::from("Skipped: a previous tool call was interrupted."),
+                    ));
+                    continue;
+                }
+
+                let _ = events.send(AgentEvent::ToolStarted(tool_call.clone()));
+                let output = if mode == AgentMode::Executing
+                    && tool_call.function.name == COMPLETE_PLAN_STEP
+                {
+                    self.complete_plan_step(&tool_call, &events)
+                } else if mode != AgentMode::Delegated && tool_call.function.name == DELEGATE_TASK {
+                    let budget = self
+                        .limits
+                        .remaining(iterations, started.elapsed(), turn_tokens);
+                    self.delegate_task(&tool_call, &events, &mut turn_tokens, budget)
+                        .await
+                } else {
+                    handle_tool_call(&tool_call, &self.tools, mode, &self.config.hooks, &events)
+                        .await
+                };
+
+                if output
+                    .as_ref()
+                    .is_ok_and(|output| !output.files_touched.is_empty())
+                {
+                    files_changed = true;
+                }
+
+                let content = match &output {
+                    Ok(output) => output.to_message(),
+                    Err(MinerveError::Permission(reason)) => {
+                        stopped = true;
+                        reason.clone()
+                    }
+                    // The model gets the error and can correct itself
+                    Err(err) => format!("[Error] {}", err),
+                };
+                messages
+                    .lock()
+                    .unwrap()
+                    .push(tool_response_message(&tool_call, content));
+
+                let _ = events.send(AgentEvent::ToolFinished {
+                    call: tool_call,
+                    output,
+                });
+            }
+
+            if stopped {
+                break StopReason::Completed;
+            }
+        };
+
+        let _ = events.send(AgentEvent::Done(reason));
+        reason
+    }
+}
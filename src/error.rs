@@ -0,0 +1,132 @@
+use reqwest::StatusCode;
+use std::fmt;
+use std::time::Duration;
+
+/// Failures of the API client, the agent loop and the tools.
+///
+/// Callers match on the kind, e.g. to retry rate limits or to stop a turn
+/// when the user declined a command, instead of parsing messages.
+#[derive(Debug)]
+pub enum MinerveError {
+    /// The request could not be sent or its body could not be read.
+    Transport(reqwest::Error),
+    /// The provider answered with a non-success status.
+    Api {
+        status: StatusCode,
+        message: String,
+        retry_after: Option<Duration>,
+    },
+    /// The provider answered without any choice.
+    EmptyResponse,
+    /// A replayed cassette could not answer the request.
+    Replay(String),
+    /// The model called a tool that is not registered.
+    UnknownTool(String),
+    /// A tool was called with missing or malformed arguments.
+    ToolArgument(String),
+    /// Reading or writing a file or a process failed.
+    Io {
+        action: String,
+        source: std::io::Error,
+    },
+    /// The user refused to let a tool run.
+    Permission(String),
+    /// An approval policy or a pre-tool hook refused to let a tool run.
+    Denied(String),
+    /// The tool exists but cannot be used in the current mode.
+    ToolUnavailable(String),
+    /// An MCP server could not be started, or broke the protocol.
+    Mcp(String),
+    /// A setting from the environment is missing or invalid.
+    Config(String),
+    /// A session or config file is not valid JSON, or could not be written as JSON.
+    Json {
+        action: String,
+        source: serde_json::Error,
+    },
+}
+
+impl MinerveError {
+    pub fn io(action: impl Into<String>, source: std::io::Error) -> Self {
+        MinerveError::Io {
+            action: action.into(),
+            source,
+        }
+    }
+
+    pub fn json(action: impl Into<String>, source: serde_json::Error) -> Self {
+        MinerveError::Json {
+            action: action.into(),
+            source,
+        }
+    }
+
+    /// Rate limits, server errors and dropped connections are worth retrying.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            MinerveError::Transport(err) => {
+                err.is_connect() || err.is_timeout() || err.is_request() || err.is_body()
+            }
+            MinerveError::Api { status, .. } => {
+                *status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+            }
+            _ => false,
+        }
+    }
+
+    /// Stable name of the kind, for machine-readable output.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            MinerveError::Transport(_) => "transport",
+            MinerveError::Api { .. } => "api",
+            MinerveError::EmptyResponse => "empty_response",
+            MinerveError::Replay(_) => "replay",
+            MinerveError::UnknownTool(_) => "unknown_tool",
+            MinerveError::ToolArgument(_) => "tool_argument",
+            MinerveError::Io { .. } => "io",
+            MinerveError::Permission(_) => "permission",
+            MinerveError::Denied(_) => "denied",
+            MinerveError::ToolUnavailable(_) => "tool_unavailable",
+            MinerveError::Mcp(_) => "mcp",
+            MinerveError::Config(_) => "config",
+            MinerveError::Json { .. } => "json",
+        }
+    }
+
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            MinerveError::Api { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for MinerveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MinerveError::Transport(err) => write!(f, "{}", err),
+            MinerveError::Api {
+                status, message, ..
+            } => write!(f, "API error {}: {}", status, message),
+            MinerveError::EmptyResponse => write!(f, "The API returned no choices"),
+            MinerveError::Replay(message) => write!(f, "Replay error: {}", message),
+            MinerveError::UnknownTool(name) => write!(f, "Function '{}' not found", name),
+            MinerveError::ToolArgument(message) => write!(f, "{}", message),
+            MinerveError::Io { action, source } => write!(f, "{}: {}", action, source),
+            MinerveError::Permission(message) => write!(f, "{}", message),
+            MinerveError::Denied(message) => write!(f, "{}", message),
+            MinerveError::ToolUnavailable(message) => write!(f, "{}", message),
+            MinerveError::Mcp(message) => write!(f, "MCP error: {}", message),
+            MinerveError::Config(message) => write!(f, "{}", message),
+            MinerveError::Json { action, source } => write!(f, "{}: {}", action, source),
+        }
+    }
+}
+
+impl std::error::Error for MinerveError {}
+
+impl From<reqwest::Error> for MinerveError {
+    fn from(err: reqwest::Error) -> Self {
+        MinerveError::Transport(err)
+    }
+}
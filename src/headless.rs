@@ -0,0 +1,413 @@
+use std::io::{IsTerminal, Write};
+
+use serde_json::{json, Value};
+
+use crate::approval::CommandApprover;
+use crate::chat::ChatCompletionToolCall;
+use crate::cli::OutputFormat;
+use crate::engine::{AgentEvent, Approval, EventReceiver, StopReason, MAX_REQUEST_RETRIES};
+use crate::error::MinerveError;
+use crate::token_counter::TokenCounter;
+use crate::tools::ToolOutput;
+
+/// How a headless run reports its progress and answers approval requests.
+pub struct HeadlessOptions {
+    pub output_format: OutputFormat,
+    pub approver: CommandApprover,
+}
+
+/// Asks whether to run `command` on stdin; blocks until the user answers.
+/// The question goes to stderr so that stdout only carries the output.
+fn confirm_on_stdin(command: &str) -> bool {
+    eprint!("Do you want to run the command '{}'? (y/n): ", command);
+    let _ = std::io::stderr().flush();
+
+    let mut input = String::new();
+    if std::io::stdin().read_line(&mut input).is_err() {
+        return false;
+    }
+
+    let input = input.trim().to_lowercase();
+    input == "y" || input == "yes"
+}
+
+/// Answers with the approval policy, asking on stdin only when the policy
+/// leaves the decision to the user and stdin is a terminal.
+async fn answer_approval(approver: &CommandApprover, command: &str) -> Approval {
+    if let Some(approval) = approver.decide(command) {
+        return approval;
+    }
+    if !std::io::stdin().is_terminal() {
+        return approver.deny_unattended(command);
+    }
+
+    let command = command.to_string();
+    let approved = tokio::task::spawn_blocking(move || confirm_on_stdin(&command))
+        .await
+        .unwrap_or(false);
+    if approved {
+        Approval::Approved
+    } else {
+        Approval::Refused
+    }
+}
+
+/// Prints the events of a headless turn, or collects the assistant's answers
+/// when `capture_output` is set.
+pub async fn print_text_events(
+    mut events: EventReceiver,
+    approver: &CommandApprover,
+    capture_output: bool,
+) -> String {
+    let mut output_buffer = Vec::new();
+    let mut tokens = (0, 0);
+
+    while let Some(event) = events.recv().await {
+        match event {
+            AgentEvent::TextDelta(delta) => {
+                if !capture_output {
+                    print!("{}", delta);
+                    let _ = std::io::stdout().flush();
+                }
+            }
+            AgentEvent::AssistantMessage(message) => {
+                if let Some(content) = message.content.filter(|c| !c.is_empty()) {
+                    if capture_output {
+                        output_buffer.push(content);
+                    } else {
+                        println!();
+                    }
+                }
+            }
+            AgentEvent::Status(message) => {
+                if !capture_output {
+                    eprintln!("{}", message);
+                }
+            }
+            AgentEvent::Retrying {
+                reason,
+                attempt,
+                delay,
+            } => {
+                eprintln!(
+                    "{} - retrying in {}s (attempt {}/{})",
+                    reason,
+                    delay.as_secs(),
+                    attempt,
+                    MAX_REQUEST_RETRIES
+                );
+            }
+            AgentEvent::ToolStarted(call) => {
+                if !capture_output {
+                    println!("Handling function call: {}", call.function.name);
+                }
+            }
+            AgentEvent::ApprovalRequested { command, respond } => {
+                let _ = respond.send(answer_approval(approver, &command).await);
+            }
+            AgentEvent::ToolFinished {
+                call,
+                output: Err(err),
+            } => {
+                if !capture_output {
+                    eprintln!(
+                        "Error occurred in tool call {}: {}",
+                        call.function.name, err
+                    );
+                }
+            }
+            AgentEvent::ToolFinished {
+                call,
+                output: Ok(output),
+            } if !output.is_success() => {
+                if !capture_output {
+                    match output.exit_code {
+                        Some(code) => eprintln!(
+                            "Tool call {} failed with exit code {}",
+                            call.function.name, code
+                        ),
+                        None => eprintln!("Tool call {} failed", call.function.name),
+                    }
+                }
+            }
+            AgentEvent::ToolFinished { .. } | AgentEvent::PlanUpdated(_) => {}
+            AgentEvent::VerificationFinished {
+                command,
+                passed,
+                output,
+            } => {
+                if !capture_output {
+                    if passed {
+                        eprintln!("Verification passed: {}", command);
+                    } else {
+                        eprintln!("Verification failed: {}\n{}", command, output);
+                    }
+                }
+            }
+            AgentEvent::Usage {
+                prompt_tokens,
+                completion_tokens,
+            } => {
+                tokens.0 += prompt_tokens;
+                tokens.1 += completion_tokens;
+            }
+            AgentEvent::Error(err) => {
+                let error_msg = format!("Request Error: {err}");
+                if capture_output {
+                    output_buffer.push(error_msg);
+                } else {
+                    eprintln!("{error_msg}");
+                }
+            }
+            AgentEvent::Done(StopReason::LimitReached(limit)) => {
+                eprintln!("Stopped: reached the {}.", limit);
+                break;
+            }
+            AgentEvent::Done(_) => break,
+        }
+    }
+
+    if capture_output {
+        output_buffer.join("\n")
+    } else {
+        eprintln!("Tokens sent: {}, received: {}", tokens.0, tokens.1);
+        String::new()
+    }
+}
+
+fn error_json(err: &MinerveError) -> Value {
+    json!({ "kind": err.kind(), "message": err.to_string() })
+}
+
+/// Tool arguments as sent by the model: an object when they parse, the raw
+/// string otherwise.
+fn arguments_json(arguments: &str) -> Value {
+    serde_json::from_str(arguments).unwrap_or_else(|_| Value::String(arguments.to_string()))
+}
+
+/// A finished tool call, as listed in the report and its `tool_finished` event.
+fn tool_call_json(
+    call: &ChatCompletionToolCall,
+    output: &Result<ToolOutput, MinerveError>,
+) -> Value {
+    let mut value = json!({
+        "id": call.id,
+        "name": call.function.name,
+        "arguments": arguments_json(&call.function.arguments),
+    });
+    match output {
+        Ok(output) => {
+            value["status"] = json!(output.status.name());
+            value["output"] = json!(output.content);
+            value["truncated"] = json!(output.truncated);
+            value["files_touched"] = json!(output.files_touched);
+            if let Some(code) = output.exit_code {
+                value["exit_code"] = json!(code);
+            }
+        }
+        Err(err) => {
+            value["status"] = json!("error");
+            value["error"] = error_json(err);
+        }
+    }
+    value
+}
+
+/// The JSON line for `event`. Approvals are answered by the caller, which
+/// reports the answer instead.
+fn event_json(event: &AgentEvent) -> Value {
+    match event {
+        AgentEvent::TextDelta(text) => json!({ "type": "text_delta", "text": text }),
+        AgentEvent::AssistantMessage(message) => json!({
+            "type": "assistant_message",
+            "content": message.content,
+            "tool_calls": message.tool_calls.iter().flatten().map(|call| json!({
+                "id": call.id,
+                "name": call.function.name,
+                "arguments": arguments_json(&call.function.arguments),
+            })).collect::<Vec<_>>(),
+        }),
+        AgentEvent::Status(message) => json!({ "type": "status", "message": message }),
+        AgentEvent::Retrying {
+            reason,
+            attempt,
+            delay,
+        } => json!({
+            "type": "retrying",
+            "reason": reason,
+            "attempt": attempt,
+            "max_attempts": MAX_REQUEST_RETRIES,
+            "delay_seconds": delay.as_secs(),
+        }),
+        AgentEvent::ToolStarted(call) => json!({
+            "type": "tool_started",
+            "id": call.id,
+            "name": call.function.name,
+            "arguments": arguments_json(&call.function.arguments),
+        }),
+        AgentEvent::ApprovalRequested { command, .. } => {
+            json!({ "type": "approval_requested", "command": command })
+        }
+        AgentEvent::ToolFinished { call, output } => {
+            let mut value = tool_call_json(call, output);
+            value["type"] = json!("tool_finished");
+            value
+        }
+        AgentEvent::Usage {
+            prompt_tokens,
+            completion_tokens,
+        } => json!({
+            "type": "usage",
+            "prompt_tokens": prompt_tokens,
+            "completion_tokens": completion_tokens,
+        }),
+        AgentEvent::VerificationFinished {
+            command,
+            passed,
+            output,
+        } => json!({
+            "type": "verification",
+            "command": command,
+            "passed": passed,
+            "output": output,
+        }),
+        AgentEvent::PlanUpdated(plan) => json!({
+            "type": "plan_updated",
+            "steps": plan.steps.iter().map(|step| json!({
+                "description": step.description,
+                "done": step.done,
+            })).collect::<Vec<_>>(),
+        }),
+        AgentEvent::Error(err) => {
+            let mut value = error_json(err);
+            value["type"] = json!("error");
+            value
+        }
+        AgentEvent::Done(reason) => {
+            let mut value = json!({ "type": "done", "exit_reason": reason.name() });
+            if let StopReason::LimitReached(limit) = reason {
+                value["limit"] = json!(limit.to_string());
+            }
+            value
+        }
+    }
+}
+
+/// What `--output-format json` prints once the turn is over.
+#[derive(Default)]
+struct JsonReport {
+    answer: Option<String>,
+    tool_calls: Vec<(ChatCompletionToolCall, Result<ToolOutput, MinerveError>)>,
+    files_changed: Vec<String>,
+    verification: Option<(String, bool)>,
+    error: Option<MinerveError>,
+    stop_reason: Option<StopReason>,
+}
+
+impl JsonReport {
+    fn record(&mut self, event: AgentEvent) {
+        match event {
+            AgentEvent::AssistantMessage(message) => {
+                if let Some(content) = message.content.filter(|c| !c.is_empty()) {
+                    self.answer = Some(content);
+                }
+            }
+            AgentEvent::ToolFinished { call, output } => {
+                let touched = output.iter().flat_map(|output| &output.files_touched);
+                for path in touched {
+                    if !self.files_changed.contains(path) {
+                        self.files_changed.push(path.clone());
+                    }
+                }
+                self.tool_calls.push((call, output));
+            }
+            AgentEvent::VerificationFinished {
+                command, passed, ..
+            } => self.verification = Some((command, passed)),
+            AgentEvent::Error(err) => self.error = Some(err),
+            AgentEvent::Done(reason) => self.stop_reason = Some(reason),
+            _ => {}
+        }
+    }
+
+    fn to_json(&self, token_counter: &TokenCounter, session_id: &str) -> Value {
+        let mut value = json!({
+            "session_id": session_id,
+            "answer": self.answer,
+            "tool_calls": self
+                .tool_calls
+                .iter()
+                .map(|(call, output)| tool_call_json(call, output))
+                .collect::<Vec<_>>(),
+            "files_changed": self.files_changed,
+            "usage": {
+                "prompt_tokens": token_counter.current_prompt(),
+                "completion_tokens": token_counter.current_completion(),
+                "cost_usd": token_counter.session_cost(),
+            },
+            "exit_reason": self.stop_reason.map(|reason| reason.name()),
+        });
+        if let Some(StopReason::LimitReached(limit)) = self.stop_reason {
+            value["limit"] = json!(limit.to_string());
+        }
+        if let Some((command, passed)) = &self.verification {
+            value["verification"] = json!({ "command": command, "passed": passed });
+        }
+        if let Some(error) = &self.error {
+            value["error"] = error_json(error);
+        }
+        value
+    }
+}
+
+fn print_json_line(value: &Value) {
+    println!("{}", value);
+    let _ = std::io::stdout().flush();
+}
+
+/// Prints the events of a headless turn as JSON: one line per event for
+/// `stream-json`, or a single object at the end for `json`.
+pub async fn print_json_events(
+    mut events: EventReceiver,
+    options: &HeadlessOptions,
+    token_counter: &TokenCounter,
+    session_id: &str,
+) {
+    let stream = options.output_format == OutputFormat::StreamJson;
+    let mut report = JsonReport::default();
+
+    if stream {
+        println!("{}", json!({ "type": "session", "session_id": session_id }));
+    }
+
+    while let Some(event) = events.recv().await {
+        let done = matches!(event, AgentEvent::Done(_));
+        match event {
+            AgentEvent::ApprovalRequested { command, respond } => {
+                let approval = answer_approval(&options.approver, &command).await;
+                let mut value = json!({
+                    "type": "approval",
+                    "command": command,
+                    "approved": approval == Approval::Approved,
+                });
+                if let Approval::Denied(reason) = &approval {
+                    value["reason"] = json!(reason);
+                }
+                let _ = respond.send(approval);
+                if stream {
+                    print_json_line(&value);
+                }
+            }
+            event if stream => print_json_line(&event_json(&event)),
+            event => report.record(event),
+        }
+
+        if done {
+            break;
+        }
+    }
+
+    if !stream {
+        println!("{}", report.to_json(token_counter, session_id));
+    }
+}
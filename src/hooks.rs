@@ -0,0 +1,123 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+
+use crate::error::MinerveError;
+use crate::tools::utils::spawn_in_group;
+use crate::tools::ToolOutput;
+
+/// Shell command run before or after tool calls.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Hook {
+    pub command: String,
+    /// Tools the hook applies to; every tool when empty.
+    #[serde(default)]
+    pub tools: Vec<String>,
+}
+
+impl Hook {
+    fn applies_to(&self, tool: &str) -> bool {
+        self.tools.is_empty() || self.tools.iter().any(|name| name == tool)
+    }
+
+    /// Runs the hook with `input` as JSON on stdin and returns whether it
+    /// succeeded, with its stdout and stderr.
+    async fn run(&self, input: &Value) -> Result<(bool, String), MinerveError> {
+        let spawn_error =
+            |err| MinerveError::io(format!("Failed to run hook `{}`", self.command), err);
+
+        let mut command = tokio::process::Command::new("sh");
+        command
+            .args(["-c", &self.command])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let (mut child, group) = spawn_in_group(&mut command).map_err(spawn_error)?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            // A hook may exit without reading its input
+            let _ = stdin.write_all(input.to_string().as_bytes()).await;
+        }
+
+        let out = child.wait_with_output().await.map_err(spawn_error)?;
+        group.disarm();
+        let output = format!(
+            "{}{}",
+            String::from_utf8_lossy(&out.stdout),
+            String::from_utf8_lossy(&out.stderr)
+        );
+        Ok((out.status.success(), output.trim().to_string()))
+    }
+}
+
+/// Hooks from the user and project config, run by `handle_tool_call`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct HooksConfig {
+    /// Run before the tool; one that fails vetoes the call.
+    pub pre_tool: Vec<Hook>,
+    /// Run after the tool, whatever its outcome.
+    pub post_tool: Vec<Hook>,
+}
+
+impl HooksConfig {
+    /// Runs the pre-tool hooks in order. The first one that fails vetoes
+    /// the call, and its output is the reason given to the model.
+    pub async fn run_pre_tool(&self, tool: &str, arguments: &Value) -> Result<(), MinerveError> {
+        let input = json!({ "tool": tool, "arguments": arguments });
+
+        for hook in self.pre_tool.iter().filter(|hook| hook.applies_to(tool)) {
+            let (success, output) = hook.run(&input).await?;
+            if !success {
+                let reason = if output.is_empty() {
+                    format!("{} was blocked by the hook `{}`.", tool, hook.command)
+                } else {
+                    output
+                };
+                return Err(MinerveError::Denied(reason));
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the post-tool hooks, which also get the tool's output or error,
+    /// and returns the commands of those that failed.
+    pub async fn run_post_tool(
+        &self,
+        tool: &str,
+        arguments: &Value,
+        output: &Result<ToolOutput, MinerveError>,
+    ) -> Vec<String> {
+        let mut input = json!({ "tool": tool, "arguments": arguments });
+        match output {
+            Ok(output) => {
+                input["status"] = json!(output.status.name());
+                input["output"] = json!(output.content);
+                input["files_touched"] = json!(output.files_touched);
+                if let Some(code) = output.exit_code {
+                    input["exit_code"] = json!(code);
+                }
+            }
+            Err(err) => {
+                input["status"] = json!("error");
+                input["error"] = json!(err.to_string());
+            }
+        }
+
+        let mut failed = Vec::new();
+        for hook in self.post_tool.iter().filter(|hook| hook.applies_to(tool)) {
+            if !matches!(hook.run(&input).await, Ok((true, _))) {
+                failed.push(hook.command.clone());
+            }
+        }
+        failed
+    }
+
+    /// User hooks first, then the project's.
+    pub fn merged(mut user: HooksConfig, project: HooksConfig) -> HooksConfig {
+        user.pre_tool.extend(project.pre_tool);
+        user.post_tool.extend(project.post_tool);
+        user
+    }
+}
@@ -0,0 +1,88 @@
+use std::env;
+use std::fmt;
+use std::time::Duration;
+
+pub const DEFAULT_MAX_ITERATIONS: usize = 50;
+pub const DEFAULT_MAX_TURN_DURATION: Duration = Duration::from_secs(30 * 60);
+pub const DEFAULT_MAX_TURN_TOKENS: u64 = 2_000_000;
+
+/// A limit that stopped the tool loop before the model was done.
+#[derive(Debug, Clone, Copy)]
+pub enum TurnLimit {
+    /// Requests sent to the model within the turn.
+    Iterations(usize),
+    /// Wall-clock time spent on the turn.
+    Time(Duration),
+    /// Prompt and completion tokens used by the turn.
+    Tokens(u64),
+}
+
+impl fmt::Display for TurnLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TurnLimit::Iterations(max) => write!(f, "iteration limit ({} requests)", max),
+            TurnLimit::Time(max) => write!(f, "time limit ({}s)", max.as_secs()),
+            TurnLimit::Tokens(max) => write!(f, "token limit ({} tokens)", max),
+        }
+    }
+}
+
+/// Caps on a single user turn, so that a model that keeps calling tools
+/// cannot run forever.
+#[derive(Debug, Clone, Copy)]
+pub struct TurnLimits {
+    pub max_iterations: usize,
+    pub max_duration: Duration,
+    pub max_tokens: u64,
+}
+
+impl Default for TurnLimits {
+    fn default() -> Self {
+        Self {
+            max_iterations: DEFAULT_MAX_ITERATIONS,
+            max_duration: DEFAULT_MAX_TURN_DURATION,
+            max_tokens: DEFAULT_MAX_TURN_TOKENS,
+        }
+    }
+}
+
+impl TurnLimits {
+    /// Reads `MINERVE_MAX_ITERATIONS`, `MINERVE_MAX_TURN_SECONDS` and
+    /// `MINERVE_MAX_TURN_TOKENS`, falling back to the defaults.
+    pub fn from_env() -> Self {
+        fn parse_var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            env::var(name).ok().and_then(|value| value.parse().ok())
+        }
+
+        let defaults = Self::default();
+        Self {
+            max_iterations: parse_var("MINERVE_MAX_ITERATIONS").unwrap_or(defaults.max_iterations),
+            max_duration: parse_var("MINERVE_MAX_TURN_SECONDS")
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.max_duration),
+            max_tokens: parse_var("MINERVE_MAX_TURN_TOKENS").unwrap_or(defaults.max_tokens),
+        }
+    }
+
+    /// Returns the first limit reached by a turn with the given usage.
+    pub fn exceeded(&self, iterations: usize, elapsed: Duration, tokens: u64) -> Option<TurnLimit> {
+        if iterations >= self.max_iterations {
+            Some(TurnLimit::Iterations(self.max_iterations))
+        } else if elapsed >= self.max_duration {
+            Some(TurnLimit::Time(self.max_duration))
+        } else if tokens >= self.max_tokens {
+            Some(TurnLimit::Tokens(self.max_tokens))
+        } else {
+            None
+        }
+    }
+    /// What is left of these limits after the given usage, e.g. for a
+    /// sub-agent that runs within the turn.
+    pub fn remaining(&self, iterations: usize, elapsed: Duration, tokens: u64) -> Self {
+        Self {
+            max_iterations: self.max_iterations.saturating_sub(iterations),
+            max_duration: self.max_duration.saturating_sub(elapsed),
+            max_tokens: self.max_tokens.saturating_sub(tokens),
+        }
+    }
+}
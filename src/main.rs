@@ -1,3 +1,5 @@
+use approval::CommandApprover;
+use config::Config;
 use cursive::event::EventResult;
 use cursive::theme::{BaseColor, Color, ColorStyle};
 use cursive::traits::*;
@@ -6,13 +8,18 @@ use cursive::view::scroll::Scroller;
 use cursive::views::{
     Dialog, LinearLayout, NamedView, OnEventView, ResizedView, ScrollView, TextArea, TextView,
 };
+use engine::{tool_definitions, StopReason};
+use headless::HeadlessOptions;
 use history::HistoryTracker;
 use minerve::Minerve;
+use session::{current_dir, Session, SessionStore};
 use std::sync::OnceLock;
 use std::sync::{Arc, Mutex};
 use theme::custom_theme;
-use token_counter::{get_global_token_counter, TokenCounter};
+use token_counter::TokenCounter;
 use tokio::runtime::Runtime;
+use tools::registry::ToolRegistry;
+use usage_ledger::{usage_report, UsageLedger};
 
 static GLOBAL_RUNTIME: OnceLock<Runtime> = OnceLock::new();
 
@@ -20,13 +27,26 @@ pub fn get_global_runtime() -> &'static Runtime {
     GLOBAL_RUNTIME.get_or_init(|| Runtime::new().unwrap())
 }
 
+mod approval;
 mod chat;
+mod commands;
+mod config;
+mod context;
+mod engine;
+mod error;
+mod headless;
 mod history;
+mod hooks;
+mod limits;
+mod mcp;
+mod stream;
 mod token_counter;
 mod tools;
+mod usage_ledger;
 
 use chat::*;
 use clap::Parser;
+use commands::{parse_command, TuiCommand};
 
 mod cli;
 use cli::*;
@@ -34,16 +54,34 @@ use cli::*;
 mod utils;
 
 mod minerve;
+mod plan;
+mod pricing;
+mod providers;
+mod session;
 mod theme;
 
-pub const MODEL_NAME: &str = "gpt-4o";
 pub const HISTORY_PATH: &str = ".minerve/history.json";
+pub const USAGE_LEDGER_PATH: &str = ".minerve/usage.jsonl";
+pub const SESSIONS_DIR: &str = ".minerve/sessions";
+pub const PANIC_LOG_PATH: &str = ".minerve/panic.log";
+
+fn status_line(model: &str, token_counter: &TokenCounter) -> String {
+    format!(
+        "Model: {} | Sent: {} | Received: {} | Cost: ${:.4} session, ${:.2} today",
+        model,
+        token_counter.current_prompt(),
+        token_counter.current_completion(),
+        token_counter.session_cost(),
+        token_counter.today_cost()
+    )
+}
 
 fn update_chat_ui(
     cb_sink: cursive::CbSink,
     messages: Vec<(String, String)>,
     request_in_flight: bool,
     token_counter: Arc<TokenCounter>,
+    model: String,
 ) {
     const MAX_OUTPUT_LEN: usize = 500;
 
@@ -110,34 +148,69 @@ fn update_chat_ui(
             }
 
             s.call_on_name("token_count", |view: &mut TextView| {
-                let sent = token_counter.current_prompt();
-                let received = token_counter.current_completion();
-                view.set_content(format!("Sent: {} | Received: {}", sent, received));
+                view.set_content(status_line(&model, &token_counter));
             });
         }))
         .unwrap();
 }
 
+pub fn update_status_line(
+    cb_sink: cursive::CbSink,
+    token_counter: Arc<TokenCounter>,
+    model: String,
+) {
+    let _ = cb_sink.send(Box::new(move |s| {
+        s.call_on_name("token_count", |view: &mut TextView| {
+            view.set_content(status_line(&model, &token_counter));
+        });
+    }));
+}
+
+/// Appends a streamed assistant delta to the chat view without re-rendering it.
+pub fn append_chat_delta(cb_sink: cursive::CbSink, delta: String, is_first_delta: bool) {
+    let _ = cb_sink.send(Box::new(move |s| {
+        s.call_on_name("chat", |view: &mut TextView| {
+            if is_first_delta {
+                view.append(StyledString::styled(
+                    "Minerve:\n",
+                    ColorStyle::new(Color::Dark(BaseColor::Cyan), Color::TerminalDefault),
+                ));
+            }
+            view.append(delta);
+        });
+
+        if let Some(mut scroll_view) =
+            s.find_name::<ScrollView<ResizedView<NamedView<TextView>>>>("chat_scroll")
+        {
+            scroll_view.get_scroller_mut().scroll_to_bottom();
+        }
+    }));
+}
+
 use std::fs::OpenOptions;
 use std::io::Write;
 
-pub fn run_headless(prompt: String) -> String {
-    get_global_runtime().block_on(run_headless_with_capture(prompt, false))
+pub fn run_headless(minerve: Minerve, prompt: String, options: HeadlessOptions) -> StopReason {
+    let (_, reason) =
+        get_global_runtime().block_on(run_headless_with_capture(minerve, prompt, false, options));
+    reason
 }
 
-pub async fn run_headless_with_capture(prompt: String, capture_output: bool) -> String {
-    if !capture_output {
+pub async fn run_headless_with_capture(
+    minerve: Minerve,
+    prompt: String,
+    capture_output: bool,
+    options: HeadlessOptions,
+) -> (String, StopReason) {
+    let print_progress = !capture_output && options.output_format == OutputFormat::Text;
+    if print_progress {
         println!("run_headless started with prompt: {}", prompt);
     }
 
-    // Create a Minerve instance to reuse existing initialization logic
-    let minerve = Minerve::new();
-
     let user_message = ChatCompletionMessage {
         role: ChatCompletionMessageRole::User,
         content: Some(prompt.clone()),
         name: None,
-        function_call: None,
         tool_call_id: None,
         tool_calls: None,
     };
@@ -149,25 +222,79 @@ pub async fn run_headless_with_capture(prompt: String, capture_output: bool) ->
     }
 
     // Use minerve's chat_headless method
-    let result = minerve.chat_headless(capture_output).await;
+    let (result, reason) = minerve.chat_headless(&options, capture_output).await;
 
-    if capture_output {
-        result
-    } else {
+    if print_progress {
+        eprintln!("Session: {}", minerve.session_id());
         println!("run_headless completed.");
-        String::new()
     }
+    (result, reason)
+}
+
+/// The session picked with `--resume` or `--continue`, if any.
+fn session_to_resume(cli: &Cli) -> Result<Option<Session>, String> {
+    if cli.resume.is_none() && !cli.continue_session {
+        return Ok(None);
+    }
+
+    let sessions =
+        SessionStore::open_default().ok_or_else(|| format!("Could not open ~/{}", SESSIONS_DIR))?;
+    if let Some(id) = &cli.resume {
+        return sessions.load(id).map(Some).map_err(|err| err.to_string());
+    }
+
+    let cwd = current_dir();
+    sessions
+        .latest_in(&cwd)
+        .map(Some)
+        .ok_or_else(|| format!("No session to continue in {}", cwd))
+}
+
+/// The session's tools, MCP servers included. Servers that fail to start are
+/// reported on stderr and left out.
+fn load_tools(config: &Config) -> ToolRegistry {
+    let mut tools = ToolRegistry::from_config(&config.tools);
+    let errors = get_global_runtime().block_on(tools.connect_mcp_servers(&config.mcp_servers));
+    for err in errors {
+        eprintln!("{}", err);
+    }
+    tools
 }
 
-fn launch_tui() {
-    let is_headless = false;
+/// Loads the project config and the session picked with `--resume` or
+/// `--continue`, if any.
+fn build_minerve(cli: &Cli) -> Result<Minerve, String> {
+    let config = Config::load().map_err(|err| err.to_string())?;
+    let session = session_to_resume(cli)?;
+
+    // An explicit --model wins over the model the session was using
+    let model = cli
+        .model
+        .clone()
+        .or_else(|| session.as_ref().map(|session| session.model.clone()));
+
+    let tools = load_tools(&config);
+    let minerve = Minerve::new(model, config, tools).map_err(|err| err.to_string())?;
+    if let Some(session) = session {
+        minerve.resume(session);
+    }
+    Ok(minerve)
+}
+
+fn launch_tui(minerve: Minerve) {
     let mut siv = cursive::default();
     siv.set_theme(custom_theme());
-    let minerve = Arc::new(Minerve::new());
+    let minerve = Arc::new(minerve);
+    if minerve.messages.lock().unwrap().len() > 1 {
+        // A resumed session; runs once the views below exist
+        minerve.show_conversation(siv.cb_sink());
+    }
     let history_tracker = Arc::new(Mutex::new(HistoryTracker::new()));
-    let token_counter_ref = get_global_token_counter();
 
     let history_tracker_for_submit = history_tracker.clone();
+    let minerve_for_cancel = minerve.clone();
+    let minerve_for_ctrl_c = minerve.clone();
+    let initial_status_line = status_line(&minerve.model(), &minerve.token_counter());
 
     let submit_button = cursive::views::Button::new("Send (Tab-Enter)", move |s| {
         let content = s
@@ -180,14 +307,52 @@ fn launch_tui() {
             return;
         }
 
+        if let Some(command) = parse_command(&content) {
+            let feedback = match command {
+                Ok(TuiCommand::Model(None)) => format!("Current model: {}", minerve.model()),
+                Ok(TuiCommand::Model(Some(model))) => {
+                    minerve.set_model(model.clone());
+                    let status = status_line(&model, &minerve.token_counter());
+                    s.call_on_name("token_count", |view: &mut TextView| {
+                        view.set_content(status)
+                    });
+                    format!("Switched model to {}", model)
+                }
+                Ok(TuiCommand::Plan(task)) => {
+                    if !minerve.clone().start_planning(task, s.cb_sink().clone()) {
+                        return;
+                    }
+                    String::from("Planning: only read-only tools until you accept the plan.")
+                }
+                Ok(TuiCommand::Sessions) => {
+                    minerve.clone().show_session_picker(s.cb_sink());
+                    String::new()
+                }
+                Ok(TuiCommand::Undo) => minerve.undo_last_turn(s.cb_sink()),
+                Ok(TuiCommand::Fork) => {
+                    minerve.clone().show_fork_picker(s.cb_sink());
+                    String::new()
+                }
+                Err(err) => err,
+            };
+            s.call_on_name("status", |view: &mut TextView| view.set_content(feedback));
+            s.call_on_name("input", |view: &mut TextArea| view.set_content(""));
+            return;
+        }
+
+        // Keep the input while a turn is running, to send it afterwards
+        if !minerve
+            .clone()
+            .chat_with_arc(content.clone(), s.cb_sink().clone())
+        {
+            return;
+        }
+
         // Increment sent tokens count
         history_tracker_for_submit
             .lock()
             .unwrap()
-            .add_prompt(content.clone());
-        minerve
-            .clone()
-            .chat_with_arc(content, s.cb_sink().clone(), is_headless);
+            .add_prompt(content);
 
         // Clear input
         s.call_on_name("input", |view: &mut TextArea| view.set_content(""));
@@ -200,10 +365,7 @@ fn launch_tui() {
     });
 
     let chat_view = TextView::new("").with_name("chat").full_height();
-    let sent = token_counter_ref.current_prompt();
-    let received = token_counter_ref.current_completion();
-
-    let token_count_view = TextView::new(format!("Sent: {} | Received: {}", sent, received))
+    let token_count_view = TextView::new(initial_status_line)
         .with_name("token_count")
         .fixed_height(1);
 
@@ -242,7 +404,7 @@ fn launch_tui() {
                 view.set_content(previous_prompt)
             });
 
-            return Some(EventResult::consumed());
+            Some(EventResult::consumed())
         })
         .on_event_inner(cursive::event::Key::Down, move |s, _e| {
             let next_prompt = history_tracker_for_down
@@ -252,23 +414,23 @@ fn launch_tui() {
                 .unwrap_or_default();
             s.call_on_name("input", |view: &mut TextArea| view.set_content(next_prompt));
 
-            return Some(EventResult::consumed());
+            Some(EventResult::consumed())
         })
         .on_event_inner(cursive::event::Event::CtrlChar('a'), |s, _e| {
             s.call_on_name("input", |view: &mut TextArea| view.set_cursor(0));
-            return Some(EventResult::consumed());
+            Some(EventResult::consumed())
         })
         .on_event_inner(cursive::event::Event::CtrlChar('e'), |s, _e| {
             s.call_on_name("input", |view: &mut TextArea| {
                 view.set_cursor(view.get_content().len())
             });
-            return Some(EventResult::consumed());
+            Some(EventResult::consumed())
         })
         .on_event_inner(cursive::event::Event::CtrlChar('k'), |s, _e| {
             s.call_on_name("input", |view: &mut TextArea| {
                 view.set_content("");
             });
-            return Some(EventResult::consumed());
+            Some(EventResult::consumed())
         });
 
     let scroll_chat_view = ScrollView::new(chat_view)
@@ -278,7 +440,11 @@ fn launch_tui() {
     siv.add_fullscreen_layer(
         Dialog::around(
             LinearLayout::vertical()
-                .child(scroll_chat_view)
+                .child(
+                    LinearLayout::horizontal()
+                        .child(scroll_chat_view.full_width())
+                        .child(TextView::new("").with_name("plan_panel").max_width(40)),
+                )
                 .child(token_count_view)
                 .child(working_textview)
                 .child(status_view)
@@ -288,11 +454,21 @@ fn launch_tui() {
         .title("minerve"),
     );
 
+    // Esc interrupts the running request or tool; Ctrl-C does too, and quits when idle
+    siv.add_global_callback(cursive::event::Key::Esc, move |s| {
+        minerve_for_cancel.cancel(s.cb_sink());
+    });
+    siv.clear_global_callbacks(cursive::event::Event::CtrlChar('c'));
+    siv.set_on_pre_event(cursive::event::Event::CtrlChar('c'), move |s| {
+        if !minerve_for_ctrl_c.cancel(s.cb_sink()) {
+            s.quit();
+        }
+    });
+
     siv.run();
 }
 
 fn main() {
-    // Open panic.log file for appending
     // Create ~/.minerve folder if it doesn't exist
     if let Some(home_dir) = dirs::home_dir() {
         let minerve_dir = home_dir.join(".minerve");
@@ -308,16 +484,22 @@ fn main() {
         }
     }
 
-    let panic_log_file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open("panic.log")
-        .expect("Failed to open panic.log");
+    // Panics are appended to ~/.minerve/panic.log, as the TUI hides stderr
+    let panic_log_file = dirs::home_dir().and_then(|home_dir| {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(home_dir.join(PANIC_LOG_PATH))
+            .ok()
+    });
 
     let panic_log_file = std::sync::Mutex::new(panic_log_file);
 
     std::panic::set_hook(Box::new(move |panic_info| {
         let mut file = panic_log_file.lock().unwrap();
+        let Some(file) = file.as_mut() else {
+            return;
+        };
         let msg = match panic_info.payload().downcast_ref::<&str>() {
             Some(s) => *s,
             None => match panic_info.payload().downcast_ref::<String>() {
@@ -339,11 +521,120 @@ fn main() {
 
     let cli = Cli::parse();
 
-    if let Some(prompt) = cli.prompt {
-        let _ = run_headless(prompt);
+    if let Some(Command::Usage) = cli.command {
+        let entries = UsageLedger::open_default()
+            .map(|ledger| ledger.entries())
+            .unwrap_or_default();
+        println!("{}", usage_report(&entries));
+        return;
+    }
+
+    if let Some(Command::Tools {
+        command: ToolsCommand::List { mode },
+    }) = cli.command
+    {
+        match Config::load() {
+            Ok(config) => {
+                let tools = load_tools(&config);
+                let definitions = tool_definitions(&tools, mode);
+                println!("{}", serde_json::to_string_pretty(&definitions).unwrap());
+                return;
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(Command::McpServe { approval }) = &cli.command {
+        let config = match Config::load() {
+            Ok(config) => config,
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        };
+        let approver = CommandApprover::with_project_allowlist(
+            approval.approve,
+            approval.allow_commands.clone(),
+        );
+        let tools = ToolRegistry::from_config(&config.tools);
+        get_global_runtime().block_on(mcp::server::serve(tools, config.hooks, approver));
         return;
     }
 
+    let minerve = match build_minerve(&cli) {
+        Ok(minerve) => minerve,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(prompt) = cli.prompt {
+        let options = HeadlessOptions {
+            output_format: cli.output_format,
+            approver: CommandApprover::with_project_allowlist(
+                cli.approval.approve,
+                cli.approval.allow_commands,
+            ),
+        };
+        let reason = run_headless(minerve, prompt, options);
+        std::process::exit(reason.exit_code());
+    }
+
     // Otherwise, launch full TUI
-    launch_tui();
+    launch_tui(minerve);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cli::ApprovalPolicy;
+    use providers::cassette_provider::ReplayProvider;
+    use std::path::Path;
+
+    #[tokio::test]
+    async fn replays_a_tool_call_and_the_final_answer() {
+        let cassette = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/show_file_then_answer.jsonl");
+        let provider = ReplayProvider::from_file(&cassette).unwrap();
+        let minerve = Minerve::with_provider(
+            Arc::new(provider),
+            None,
+            Arc::new(TokenCounter::new()),
+            Config::default(),
+            ToolRegistry::from_config(&Default::default()),
+        );
+        let messages = minerve.messages.clone();
+        let options = HeadlessOptions {
+            output_format: OutputFormat::Text,
+            approver: CommandApprover::new(ApprovalPolicy::Never, Vec::new()),
+        };
+
+        let (answer, reason) = run_headless_with_capture(
+            minerve,
+            String::from("What is the crate called?"),
+            true,
+            options,
+        )
+        .await;
+
+        assert_eq!(answer, "The crate is named minerve.");
+        assert!(matches!(reason, StopReason::Completed));
+
+        let messages = messages.lock().unwrap();
+        let tool_messages: Vec<_> = messages
+            .iter()
+            .filter(|msg| matches!(msg.role, ChatCompletionMessageRole::Tool))
+            .collect();
+        assert_eq!(tool_messages.len(), 1);
+        assert_eq!(tool_messages[0].tool_call_id.as_deref(), Some("call_1"));
+        assert!(tool_messages[0]
+            .content
+            .as_deref()
+            .unwrap()
+            .contains("name = \"minerve\""));
+    }
 }
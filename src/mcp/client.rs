@@ -0,0 +1,404 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, ChildStderr, ChildStdout, Command};
+use tokio::sync::{mpsc, oneshot};
+
+use super::{
+    error_response, notification, request, response, write_messages, METHOD_NOT_FOUND,
+    PROTOCOL_VERSION,
+};
+use crate::config::McpServerConfig;
+use crate::error::MinerveError;
+use crate::tools::{Param, Tool, ToolOutput, ToolParams};
+
+/// How long a server gets to answer `initialize` and `tools/list`.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+/// Longest tool name the providers accept.
+const MAX_TOOL_NAME_LEN: usize = 64;
+/// Lines of the server's stderr kept to explain a failed handshake.
+const STDERR_TAIL_LINES: usize = 20;
+/// How long a failed handshake waits for the last of the server's stderr.
+const STDERR_GRACE: Duration = Duration::from_millis(500);
+
+type Answer = Result<Value, MinerveError>;
+/// Requests waiting for an answer, by id; `None` once the server exited.
+type PendingRequests = Arc<Mutex<Option<HashMap<u64, oneshot::Sender<Answer>>>>>;
+
+/// An MCP server running over stdio for the whole session.
+pub struct McpClient {
+    name: String,
+    outgoing: mpsc::UnboundedSender<Value>,
+    pending: PendingRequests,
+    next_id: AtomicU64,
+    /// Last lines the server wrote to stderr.
+    stderr_tail: Arc<Mutex<VecDeque<String>>>,
+    /// Reads stderr until the server closes it.
+    stderr_reader: tokio::sync::Mutex<Option<tokio::task::JoinHandle<()>>>,
+    // Killed when the client is dropped
+    _child: Child,
+}
+
+impl McpClient {
+    /// Starts the server and performs the `initialize` handshake.
+    pub async fn connect(name: &str, config: &McpServerConfig) -> Result<Arc<Self>, MinerveError> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .envs(&config.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|err| {
+                MinerveError::io(format!("Failed to start MCP server '{}'", name), err)
+            })?;
+
+        let (outgoing, outgoing_rx) = mpsc::unbounded_channel();
+        let pending: PendingRequests = Arc::new(Mutex::new(Some(HashMap::new())));
+        // Both are piped above
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let stderr = child.stderr.take().unwrap();
+        let stderr_tail = Arc::new(Mutex::new(VecDeque::new()));
+        let stderr_reader = tokio::spawn(read_stderr(stderr, stderr_tail.clone()));
+        tokio::spawn(write_messages(stdin, outgoing_rx));
+        tokio::spawn(read_messages(
+            name.to_string(),
+            stdout,
+            pending.clone(),
+            outgoing.clone(),
+        ));
+
+        let client = Arc::new(Self {
+            name: name.to_string(),
+            outgoing,
+            pending,
+            next_id: AtomicU64::new(1),
+            stderr_tail,
+            stderr_reader: tokio::sync::Mutex::new(Some(stderr_reader)),
+            _child: child,
+        });
+
+        let params = json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": { "name": "minerve", "version": env!("CARGO_PKG_VERSION") },
+        });
+        client.handshake_request("initialize", params).await?;
+        let _ = client
+            .outgoing
+            .send(notification("notifications/initialized"));
+
+        Ok(client)
+    }
+
+    fn error(&self, message: &str) -> MinerveError {
+        MinerveError::Mcp(format!("server '{}' {}", self.name, message))
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Answer {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (respond, answer) = oneshot::channel();
+        match self.pending.lock().unwrap().as_mut() {
+            Some(pending) => {
+                pending.insert(id, respond);
+            }
+            None => return Err(self.error("has exited")),
+        }
+
+        if self.outgoing.send(request(id, method, params)).is_err() {
+            return Err(self.error("has exited"));
+        }
+        // The sender is dropped when the server exits before answering
+        answer
+            .await
+            .unwrap_or_else(|_| Err(self.error("has exited")))
+    }
+
+    /// Sends a request of the handshake; a failure comes with the end of the
+    /// server's stderr, which usually tells why it did not start.
+    async fn handshake_request(&self, method: &str, params: Value) -> Answer {
+        let answer = tokio::time::timeout(HANDSHAKE_TIMEOUT, self.request(method, params))
+            .await
+            .unwrap_or_else(|_| Err(self.error(&format!("did not answer {} in time", method))));
+
+        match answer {
+            Err(MinerveError::Mcp(message)) => {
+                // A server that exited may not be done writing its stderr
+                if let Some(reader) = self.stderr_reader.lock().await.take() {
+                    let _ = tokio::time::timeout(STDERR_GRACE, reader).await;
+                }
+                let tail = Vec::from(self.stderr_tail.lock().unwrap().clone()).join("\n");
+                if tail.is_empty() {
+                    Err(MinerveError::Mcp(message))
+                } else {
+                    Err(MinerveError::Mcp(format!(
+                        "{}; its stderr:\n{}",
+                        message, tail
+                    )))
+                }
+            }
+            answer => answer,
+        }
+    }
+
+    /// Lists the server's tools, following pagination.
+    pub async fn list_tools(self: &Arc<Self>) -> Result<Vec<McpTool>, MinerveError> {
+        let mut tools = Vec::new();
+        let mut cursor = None;
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let result = self.handshake_request("tools/list", params).await?;
+
+            for tool in result["tools"].as_array().into_iter().flatten() {
+                tools.extend(McpTool::new(self.clone(), tool));
+            }
+
+            cursor = result
+                .get("nextCursor")
+                .filter(|cursor| !cursor.is_null())
+                .cloned();
+            if cursor.is_none() {
+                return Ok(tools);
+            }
+        }
+    }
+
+    /// Calls the tool `name` and returns the text of its result, failed when
+    /// the server flags it as an error.
+    pub async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Value,
+    ) -> Result<ToolOutput, MinerveError> {
+        let result = self
+            .request(
+                "tools/call",
+                json!({ "name": name, "arguments": arguments }),
+            )
+            .await?;
+
+        let text = result["content"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|content| match content["type"].as_str() {
+                Some("text") => content["text"].as_str().unwrap_or_default().to_string(),
+                Some(kind) => format!("[{} content]", kind),
+                None => content.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        if result["isError"].as_bool().unwrap_or(false) {
+            Ok(ToolOutput::error(text))
+        } else {
+            Ok(ToolOutput::success(text))
+        }
+    }
+}
+
+/// Keeps the last lines of the server's stderr, which would otherwise fill
+/// the pipe and block the server.
+async fn read_stderr(stderr: ChildStderr, tail: Arc<Mutex<VecDeque<String>>>) {
+    let mut lines = BufReader::new(stderr).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let mut tail = tail.lock().unwrap();
+        if tail.len() == STDERR_TAIL_LINES {
+            tail.pop_front();
+        }
+        tail.push_back(line);
+    }
+}
+
+/// Hands the server's answers to the pending requests until it exits.
+async fn read_messages(
+    name: String,
+    stdout: ChildStdout,
+    pending: PendingRequests,
+    outgoing: mpsc::UnboundedSender<Value>,
+) {
+    let mut lines = BufReader::new(stdout).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        // Some servers log to stdout; anything but JSON is skipped
+        let Ok(message) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+
+        match (message.get("id").cloned(), message.get("method")) {
+            // A request from the server; only pings are supported
+            (Some(id), Some(method)) => {
+                let reply = if method == "ping" {
+                    response(id, json!({}))
+                } else {
+                    error_response(id, METHOD_NOT_FOUND, "Method not supported by Minerve")
+                };
+                let _ = outgoing.send(reply);
+            }
+            (Some(id), None) => {
+                let respond = id
+                    .as_u64()
+                    .and_then(|id| pending.lock().unwrap().as_mut()?.remove(&id));
+                let answer = match message.get("error") {
+                    Some(error) => Err(MinerveError::Mcp(format!(
+                        "server '{}' answered: {}",
+                        name,
+                        error["message"].as_str().unwrap_or("unknown error")
+                    ))),
+                    None => Ok(message["result"].clone()),
+                };
+                if let Some(respond) = respond {
+                    let _ = respond.send(answer);
+                }
+            }
+            // Notifications, e.g. logs or progress
+            _ => {}
+        }
+    }
+
+    // Fails the requests still waiting, and any later one
+    pending.lock().unwrap().take();
+}
+
+/// A tool of an MCP server, offered to the model as `<server>__<tool>`.
+pub struct McpTool {
+    client: Arc<McpClient>,
+    name: String,
+    remote_name: String,
+    description: String,
+    input_schema: Value,
+    read_only: bool,
+}
+
+impl McpTool {
+    fn new(client: Arc<McpClient>, tool: &Value) -> Option<Self> {
+        let remote_name = tool["name"].as_str()?.to_string();
+        let name = format!("{}__{}", client.name, remote_name)
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .take(MAX_TOOL_NAME_LEN)
+            .collect();
+        let input_schema = Some(tool["inputSchema"].clone())
+            .filter(Value::is_object)
+            .unwrap_or_else(|| json!({ "type": "object", "properties": {} }));
+
+        Some(Self {
+            name,
+            remote_name,
+            description: tool["description"].as_str().unwrap_or_default().to_string(),
+            input_schema,
+            read_only: tool["annotations"]["readOnlyHint"]
+                .as_bool()
+                .unwrap_or(false),
+            client,
+        })
+    }
+}
+
+#[async_trait]
+impl Tool for McpTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// The server checks the arguments against its own schema.
+    fn parameters(&self) -> Vec<Param> {
+        Vec::new()
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    async fn run(&self, params: ToolParams) -> Result<ToolOutput, MinerveError> {
+        self.client
+            .call_tool(&self.remote_name, params.to_json())
+            .await
+    }
+
+    fn function_definition(&self) -> Value {
+        self.input_schema.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::ToolStatus;
+
+    fn stub_config(args: &[&str]) -> McpServerConfig {
+        let script = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/mcp_stub_server.sh"
+        );
+        McpServerConfig {
+            command: String::from("sh"),
+            args: std::iter::once(script)
+                .chain(args.iter().copied())
+                .map(String::from)
+                .collect(),
+            env: HashMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn lists_the_tools_of_every_page() {
+        let client = McpClient::connect("stub", &stub_config(&[])).await.unwrap();
+        let tools = client.list_tools().await.unwrap();
+
+        let names: Vec<&str> = tools.iter().map(|tool| tool.name()).collect();
+        assert_eq!(names, ["stub__echo", "stub__fail", "stub__exit"]);
+        assert!(tools[0].is_read_only());
+        assert!(!tools[1].is_read_only());
+    }
+
+    #[tokio::test]
+    async fn calls_tools_and_keeps_their_errors() {
+        let client = McpClient::connect("stub", &stub_config(&[])).await.unwrap();
+
+        let output = client.call_tool("echo", json!({})).await.unwrap();
+        assert_eq!(output.status, ToolStatus::Success);
+        assert_eq!(output.content, "hello");
+
+        let output = client.call_tool("fail", json!({})).await.unwrap();
+        assert_eq!(output.status, ToolStatus::Error);
+        assert_eq!(output.content, "it failed");
+    }
+
+    #[tokio::test]
+    async fn fails_pending_and_later_requests_when_the_server_exits() {
+        let client = McpClient::connect("stub", &stub_config(&[])).await.unwrap();
+
+        let err = client.call_tool("exit", json!({})).await.unwrap_err();
+        assert_eq!(err.to_string(), "MCP error: server 'stub' has exited");
+        let err = client.call_tool("echo", json!({})).await.unwrap_err();
+        assert_eq!(err.to_string(), "MCP error: server 'stub' has exited");
+    }
+
+    #[tokio::test]
+    async fn reports_stderr_when_the_handshake_fails() {
+        let Err(err) = McpClient::connect("stub", &stub_config(&["broken"])).await else {
+            panic!("the broken server connected");
+        };
+        assert!(err.to_string().contains("stub: missing STUB_TOKEN"));
+    }
+}
@@ -0,0 +1,45 @@
+use serde_json::{json, Value};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+pub mod client;
+pub mod server;
+
+/// Revision of the Model Context Protocol that Minerve speaks.
+pub const PROTOCOL_VERSION: &str = "2025-03-26";
+
+/// JSON-RPC code for a message that is not JSON.
+pub const PARSE_ERROR: i64 = -32700;
+/// JSON-RPC code for a method the peer does not handle.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// JSON-RPC code for a request with bad parameters, e.g. an unknown tool.
+pub const INVALID_PARAMS: i64 = -32602;
+
+pub fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+pub fn notification(method: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method })
+}
+
+pub fn response(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+pub fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// Writes the queued messages to the peer, one per line.
+pub async fn write_messages<W: AsyncWrite + Unpin>(
+    mut out: W,
+    mut messages: mpsc::UnboundedReceiver<Value>,
+) {
+    while let Some(message) = messages.recv().await {
+        let line = format!("{}\n", message);
+        if out.write_all(line.as_bytes()).await.is_err() || out.flush().await.is_err() {
+            break;
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::mpsc;
+
+use super::{
+    error_response, response, write_messages, INVALID_PARAMS, METHOD_NOT_FOUND, PARSE_ERROR,
+    PROTOCOL_VERSION,
+};
+use crate::approval::CommandApprover;
+use crate::chat::{ChatCompletionFunctionCall, ChatCompletionToolCall};
+use crate::engine::{event_channel, handle_tool_call, AgentEvent, AgentMode};
+use crate::hooks::HooksConfig;
+use crate::tools::registry::ToolRegistry;
+use crate::tools::Tool;
+
+type ProtocolError = (i64, String);
+
+fn tool_descriptor(tool: &dyn Tool) -> Value {
+    json!({
+        "name": tool.name(),
+        "description": tool.description(),
+        "inputSchema": tool.function_definition(),
+        "annotations": { "readOnlyHint": tool.is_read_only() },
+    })
+}
+
+fn tool_result(text: String, is_error: bool) -> Value {
+    json!({
+        "content": [{ "type": "text", "text": text }],
+        "isError": is_error,
+    })
+}
+
+/// Answers the requests of one MCP client.
+struct ToolServer {
+    tools: ToolRegistry,
+    hooks: HooksConfig,
+    approver: CommandApprover,
+}
+
+impl ToolServer {
+    async fn answer(&self, method: &str, params: Value) -> Result<Value, ProtocolError> {
+        match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "minerve", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => {
+                let tools: Vec<Value> = self
+                    .tools
+                    .tools(AgentMode::Normal)
+                    .map(|tool| tool_descriptor(tool.as_ref()))
+                    .collect();
+                Ok(json!({ "tools": tools }))
+            }
+            "tools/call" => self.call_tool(params).await,
+            _ => Err((METHOD_NOT_FOUND, format!("Method '{}' not found", method))),
+        }
+    }
+
+    /// Runs the tool like the agent loop does, hooks included. There is no
+    /// one to ask, so commands the policy leaves to the user are denied.
+    async fn call_tool(&self, params: Value) -> Result<Value, ProtocolError> {
+        let name = params["name"]
+            .as_str()
+            .ok_or_else(|| (INVALID_PARAMS, String::from("Missing tool name")))?;
+        if self.tools.get(name).is_none() {
+            return Err((INVALID_PARAMS, format!("Unknown tool: {}", name)));
+        }
+        let arguments = match &params["arguments"] {
+            Value::Null => String::new(),
+            arguments => arguments.to_string(),
+        };
+        let tool_call = ChatCompletionToolCall {
+            id: String::new(),
+            kind: String::from("function"),
+            function: ChatCompletionFunctionCall {
+                name: name.to_string(),
+                arguments,
+            },
+        };
+
+        let (events, mut events_rx) = event_channel();
+        let run = async move {
+            handle_tool_call(
+                &tool_call,
+                &self.tools,
+                AgentMode::Normal,
+                &self.hooks,
+                &events,
+            )
+            .await
+        };
+        let answer_approvals = async {
+            while let Some(event) = events_rx.recv().await {
+                match event {
+                    AgentEvent::ApprovalRequested { command, respond } => {
+                        let approval = self
+                            .approver
+                            .decide(&command)
+                            .unwrap_or_else(|| self.approver.deny_unattended(&command));
+                        let _ = respond.send(approval);
+                    }
+                    // stdout carries the protocol
+                    AgentEvent::Status(message) => eprintln!("{}", message),
+                    _ => {}
+                }
+            }
+        };
+        let (output, ()) = tokio::join!(run, answer_approvals);
+
+        Ok(match output {
+            // The same text the agent loop sends, with the exit code
+            Ok(output) => tool_result(output.to_message(), !output.is_success()),
+            Err(err) => tool_result(err.to_string(), true),
+        })
+    }
+}
+
+/// Serves `tools` over MCP on stdin and stdout, until stdin is closed.
+pub async fn serve(tools: ToolRegistry, hooks: HooksConfig, approver: CommandApprover) {
+    let server = Arc::new(ToolServer {
+        tools,
+        hooks,
+        approver,
+    });
+    let (outgoing, outgoing_rx) = mpsc::unbounded_channel();
+    let writer = tokio::spawn(write_messages(tokio::io::stdout(), outgoing_rx));
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let Ok(message) = serde_json::from_str::<Value>(&line) else {
+            let _ = outgoing.send(error_response(Value::Null, PARSE_ERROR, "Parse error"));
+            continue;
+        };
+        // Notifications and answers need no reply
+        let (Some(id), Some(method)) = (message.get("id").cloned(), message["method"].as_str())
+        else {
+            continue;
+        };
+
+        // Tool calls run concurrently, so that a slow one holds up no other
+        let server = server.clone();
+        let outgoing = outgoing.clone();
+        let method = method.to_string();
+        let params = message["params"].clone();
+        tokio::spawn(async move {
+            let reply = match server.answer(&method, params).await {
+                Ok(result) => response(id, result),
+                Err((code, message)) => error_response(id, code, &message),
+            };
+            let _ = outgoing.send(reply);
+        });
+    }
+
+    // Lets the calls still running answer before exiting
+    drop(outgoing);
+    let _ = writer.await;
+}
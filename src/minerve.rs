@@ -1,305 +1,288 @@
+use crate::cli::OutputFormat;
+use crate::config::Config;
+use crate::context::ContextManager;
+use crate::engine::{
+    event_channel, tool_response_message, AgentEngine, AgentEvent, AgentMode, Approval,
+    EventReceiver, StopReason, MAX_REQUEST_RETRIES,
+};
+use crate::error::MinerveError;
+use crate::headless::{print_json_events, print_text_events, HeadlessOptions};
+use crate::limits::{TurnLimit, TurnLimits};
+use crate::plan::{Plan, PLANNING_ENDED_NOTE, PLANNING_PROMPT};
+use crate::providers::{provider_from_env, LlmProvider};
+use crate::session::{current_dir, summary_line, Session, SessionStore, SessionUsage};
 use crate::token_counter::TokenCounter;
+use crate::tools::registry::ToolRegistry;
+use crate::tools::ToolOutput;
+use crate::usage_ledger::UsageLedger;
 use cursive::views::{ResizedView, TextView};
 use dotenvy::from_path;
-use reqwest::Client;
-use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+const SHELL_CONFIRM_DIALOG: &str = "shell_confirm_dialog";
+const INTERRUPTED_NOTE: &str = "[Interrupted by user]";
+const PLAN_EDITOR: &str = "plan_editor";
+
+fn show_working_status(cb_sink: &cursive::CbSink, message: String) {
+    let _ = cb_sink.send(Box::new(move |s| {
+        if let Some(mut view) = s.find_name::<ResizedView<TextView>>("working_textview") {
+            view.get_inner_mut().set_content(message);
+        }
+    }));
+}
+
+fn show_status(cb_sink: &cursive::CbSink, message: String) {
+    let _ = cb_sink.send(Box::new(move |s| {
+        s.call_on_name("status", |view: &mut TextView| view.set_content(message));
+    }));
+}
+
+/// What the status line says about a finished tool call: failures, files
+/// changed and truncated output. Plain successes are not worth a line.
+fn tool_outcome(name: &str, output: &Result<ToolOutput, MinerveError>) -> Option<String> {
+    let output = match output {
+        Ok(output) => output,
+        Err(err) => return Some(format!("{} failed: {}", name, err)),
+    };
+
+    let mut notes = Vec::new();
+    match (output.is_success(), output.exit_code) {
+        (false, Some(code)) => notes.push(format!("failed with exit code {}", code)),
+        (false, None) => notes.push(String::from("failed")),
+        (true, _) => {}
+    }
+    if !output.files_touched.is_empty() {
+        notes.push(format!("changed {}", output.files_touched.join(", ")));
+    }
+    if output.truncated {
+        notes.push(String::from("output truncated"));
+    }
+
+    if notes.is_empty() {
+        None
+    } else {
+        Some(format!("{}: {}", name, notes.join(", ")))
+    }
+}
+
+/// Shows the plan with its completed steps in the side panel.
+fn show_plan_progress(cb_sink: &cursive::CbSink, plan: &Plan) {
+    let content = format!("Plan\n\n{}", plan.progress());
+    let _ = cb_sink.send(Box::new(move |s| {
+        s.call_on_name("plan_panel", |view: &mut TextView| {
+            view.set_content(content)
+        });
+    }));
+}
 
-pub async fn post_request_with_token_count(
-    client: &Client,
-    url: &str,
-    api_key: &str,
-    request: ChatCompletionRequest,
-    _cb_sink: Option<&cursive::CbSink>,
-    token_counter: Arc<TokenCounter>,
-) -> Result<ChatCompletionResponse, reqwest::Error> {
-    let response = client
-        .post(url)
-        .header("Authorization", format!("Bearer {}", api_key))
-        .header("Content-Type", "application/json")
-        .json(&request)
-        .send()
-        .await?;
-
-    let chat_response: ChatCompletionResponse = response.json().await?;
-
-    if let Some(ref usage) = chat_response.usage {
-        // Correctly use the increment with the provided token_counter
-        token_counter.increment_prompt(usage.prompt_tokens as usize);
-        token_counter.increment_completion(usage.completion_tokens as usize);
-    }
-
-    Ok(chat_response)
+/// Counts down in the working indicator while the engine waits before a retry.
+async fn show_retry_countdown(
+    cb_sink: &cursive::CbSink,
+    reason: &str,
+    attempt: u32,
+    delay: Duration,
+) {
+    let mut remaining = delay.as_secs();
+    while remaining > 0 {
+        show_working_status(
+            cb_sink,
+            format!(
+                "{}\nretrying in {}s (attempt {}/{})",
+                reason, remaining, attempt, MAX_REQUEST_RETRIES
+            ),
+        );
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        remaining -= 1;
+    }
+    show_working_status(cb_sink, String::from("working..."));
+}
+
+/// Asks whether to run `command` in a dialog and answers on `respond`.
+fn show_approval_dialog(
+    cb_sink: &cursive::CbSink,
+    command: String,
+    respond: oneshot::Sender<Approval>,
+) {
+    use cursive::traits::Nameable;
+    use cursive::views::Dialog;
+
+    // The dialog buttons may fire more than once, so the sender is taken on first use
+    let respond = Arc::new(Mutex::new(Some(respond)));
+    let respond_yes = respond.clone();
+    let respond_no = respond.clone();
+
+    let _ = cb_sink.send(Box::new(move |s| {
+        s.add_layer(
+            Dialog::text(format!(
+                "Accept running the following shell command?\n{}",
+                command
+            ))
+            .button("Yes", move |s| {
+                s.pop_layer();
+                if let Some(tx) = respond_yes.lock().unwrap().take() {
+                    let _ = tx.send(Approval::Approved);
+                }
+            })
+            .button("No", move |s| {
+                s.pop_layer();
+                if let Some(tx) = respond_no.lock().unwrap().take() {
+                    let _ = tx.send(Approval::Refused);
+                }
+            })
+            .with_name(SHELL_CONFIRM_DIALOG),
+        );
+    }));
 }
 
 use std::env;
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
+use tokio::task::JoinHandle;
 
-const HIST_CUTOFF: usize = 30;
-
-use crate::tools::registry::get_tool_registry;
-use crate::tools::run_shell_command_tool::RunShellCommandTool;
 use crate::{
-    update_chat_ui, ChatCompletionFunctionCall, ChatCompletionFunctionDefinition,
-    ChatCompletionMessage, ChatCompletionMessageRole, ChatCompletionRequest,
-    ChatCompletionResponse, ToolCallResult, MODEL_NAME,
+    append_chat_delta, update_chat_ui, update_status_line, ChatCompletionMessage,
+    ChatCompletionMessageRole, ChatCompletionToolCall,
 };
 
 pub struct Minerve {
     pub messages: Arc<Mutex<Vec<ChatCompletionMessage>>>,
-    pub client: Client,
-    pub api_key: String,
-    pub base_url: String,
+    pub engine: Arc<AgentEngine>,
+    pub current_task: Arc<Mutex<Option<JoinHandle<()>>>>,
     pub request_in_flight: Arc<AtomicBool>,
-    pub token_counter: Arc<TokenCounter>,
+    session: Mutex<ActiveSession>,
+    sessions: Option<SessionStore>,
 }
 
-pub fn get_system_prompt() -> String {
-    return String::from(include_str!("../prompts/MAIN_SYSTEM_PROMPT.txt"));
+/// The session being recorded. Its messages live in `Minerve::messages`.
+struct ActiveSession {
+    session: Session,
+    /// The token counter's totals when the session was started or resumed.
+    counted_at_start: SessionUsage,
 }
 
-pub async fn handle_tool_call(
-    tool_call: &ChatCompletionFunctionCall,
-    cb_sink: Option<cursive::CbSink>,
-    is_headless: bool,
-) -> ToolCallResult {
-    let settings = crate::tools::ExecuteCommandSettings { is_headless };
-    let registry = get_tool_registry();
-    let tool_name = &tool_call.name;
-    let args_str = &tool_call.arguments;
-
-    if let Some(tool) = registry.get(tool_name.as_str()) {
-        // Parse as generic JSON value first, then convert all values to strings
-        let args: HashMap<String, String> =
-            match serde_json::from_str::<serde_json::Value>(args_str) {
-                Ok(serde_json::Value::Object(map)) => map
-                    .into_iter()
-                    .map(|(k, v)| {
-                        let string_value = match v {
-                            serde_json::Value::String(s) => s,
-                            serde_json::Value::Number(n) => n.to_string(),
-                            serde_json::Value::Bool(b) => b.to_string(),
-                            _ => v.to_string(),
-                        };
-                        (k, string_value)
-                    })
-                    .collect(),
-                _ => HashMap::new(),
-            };
-
-        if tool_name.as_str() == "run_shell_command" {
-            if let Some(cb_sink) = &cb_sink {
-                use cursive::views::Dialog;
-                use std::sync::mpsc::sync_channel;
-
-                let (tx, rx) = sync_channel::<bool>(0);
-                let command = args.get("command").unwrap_or(&String::new()).clone();
-
-                let tx_yes = tx.clone();
-                let tx_no = tx.clone();
-                let command_for_dialog = command.clone();
-
-                // Send confirmation dialog to main UI
-                let cb_sink_clone = cb_sink.clone();
-                cb_sink_clone
-                    .send(Box::new(move |s| {
-                        s.add_layer(
-                            Dialog::text(format!(
-                                "Accept running the following shell command?\n{}",
-                                command_for_dialog
-                            ))
-                            .button("Yes", move |s| {
-                                s.pop_layer();
-                                let _ = tx_yes.send(true);
-                            })
-                            .button("No", move |s| {
-                                s.pop_layer();
-                                let _ = tx_no.send(false);
-                            }),
-                        );
-                    }))
-                    .unwrap();
-
-                // Wait for user confirmation
-                let confirmed = rx.recv().unwrap_or(false);
-                if !confirmed {
-                    return ToolCallResult::Cancelled;
-                }
-
-                let output = RunShellCommandTool::execute_command(&command, Some(settings));
-
-                return ToolCallResult::Success(ChatCompletionMessage {
-                    role: ChatCompletionMessageRole::Function,
-                    content: Some(output),
-                    name: Some(tool_name.clone()),
-                    function_call: None,
-                    tool_call_id: Some(tool_call.name.clone()),
-                    tool_calls: None,
-                });
-            }
-        }
+pub fn get_system_prompt() -> String {
+    String::from(include_str!("../prompts/MAIN_SYSTEM_PROMPT.txt"))
+}
 
-        let function_name_for_indicator = tool_name.clone();
+/// Answers the tool calls of the last assistant message that have no response yet,
+/// so that the conversation stays valid after an interrupted turn.
+fn answer_dangling_tool_calls(messages: &mut Vec<ChatCompletionMessage>) {
+    let Some(last_call_index) = messages.iter().rposition(|msg| msg.tool_calls.is_some()) else {
+        return;
+    };
+
+    let answered: Vec<String> = messages[last_call_index + 1..]
+        .iter()
+        .filter_map(|msg| msg.tool_call_id.clone())
+        .collect();
+
+    let unanswered: Vec<ChatCompletionToolCall> = messages[last_call_index]
+        .tool_calls
+        .iter()
+        .flatten()
+        .filter(|tool_call| !answered.contains(&tool_call.id))
+        .cloned()
+        .collect();
+
+    for tool_call in unanswered {
+        messages.push(tool_response_message(
+            &tool_call,
+            String::from("Interrupted by user."),
+        ));
+    }
+}
 
-        // Show working indicator
-        if let Some(cb_sink) = &cb_sink {
-            let _ = cb_sink.send(Box::new(move |s| {
-                if let Some(mut view) = s.find_name::<ResizedView<TextView>>("working_textview") {
-                    let message = format!("Running tool: {}", function_name_for_indicator);
-                    view.get_inner_mut().set_content(message);
-                } else {
-                    panic!("working_textview view not found");
-                }
-            }));
-        }
+/// Where the turn started by the user message at `user_index` begins: the
+/// planning instructions sent with a `/plan` task belong to its turn.
+fn turn_start(messages: &[ChatCompletionMessage], user_index: usize) -> usize {
+    match user_index.checked_sub(1).map(|index| &messages[index]) {
+        Some(previous) if previous.content.as_deref() == Some(PLANNING_PROMPT) => user_index - 1,
+        _ => user_index,
+    }
+}
 
-        let result = tool.run(args, settings).await;
+/// Removes the last user turn and everything after it, returning its prompt.
+/// Verification reports belong to the turn whose edits they check.
+fn remove_last_turn(messages: &mut Vec<ChatCompletionMessage>) -> Option<String> {
+    let user_index = messages
+        .iter()
+        .rposition(ChatCompletionMessage::is_user_prompt)?;
+    let prompt = messages[user_index].content.clone().unwrap_or_default();
+    messages.truncate(turn_start(messages, user_index));
+    Some(prompt)
+}
 
-        let function_name_for_indicator = tool_name.clone();
+fn clear_plan_panel(cb_sink: &cursive::CbSink) {
+    let _ = cb_sink.send(Box::new(|s| {
+        s.call_on_name("plan_panel", |view: &mut TextView| view.set_content(""));
+    }));
+}
 
-        // Hide working indicator
-        if let Some(cb_sink) = &cb_sink {
-            let _ = cb_sink.send(Box::new(move |s| {
-                if let Some(mut view) = s.find_name::<ResizedView<TextView>>("working_textview") {
-                    let message = format!("Reading tool result: {}", function_name_for_indicator);
-                    view.get_inner_mut().set_content(message);
-                } else {
-                    panic!("working_textview view not found");
-                }
-            }));
-        }
+/// Puts `text` in the input, e.g. to edit a prompt before sending it again.
+fn put_in_input(cb_sink: &cursive::CbSink, text: String) {
+    let _ = cb_sink.send(Box::new(move |s| {
+        s.call_on_name("input", |view: &mut cursive::views::TextArea| {
+            view.set_content(text)
+        });
+    }));
+}
 
-        ToolCallResult::Success(ChatCompletionMessage {
-            role: ChatCompletionMessageRole::Function,
-            content: Some(result),
-            name: Some(tool_name.clone()),
-            function_call: None,
-            tool_call_id: Some(tool_call.name.clone()),
-            tool_calls: None,
+/// Maps conversation messages to the (role, content) pairs shown in the chat view.
+fn to_ui_messages(messages: &[ChatCompletionMessage]) -> Vec<(String, String)> {
+    messages
+        .iter()
+        .filter(|msg| msg.content.as_ref().is_some_and(|c| !c.is_empty()))
+        .map(|msg| {
+            let role = match msg.role {
+                ChatCompletionMessageRole::System => "system".to_string(),
+                ChatCompletionMessageRole::User => "user".to_string(),
+                ChatCompletionMessageRole::Assistant => "minerve".to_string(),
+                ChatCompletionMessageRole::Tool => msg
+                    .name
+                    .clone()
+                    .unwrap_or(String::from("unknown tool call")),
+            };
+            (role, msg.content.clone().unwrap_or_default())
         })
-    } else {
-        ToolCallResult::Error(format!("Function '{}' not found", tool_name))
-    }
+        .collect()
 }
 
 impl Minerve {
-    pub async fn chat_headless(&self, capture_output: bool) -> String {
-        let mut output_buffer = Vec::new();
-        let is_headless = true;
-
-        let mut history: Vec<ChatCompletionMessage> = {
-            let msgs = self.messages.lock().unwrap();
-            msgs.clone()
-        };
-
-        let registry = get_tool_registry();
-        let functions: Vec<ChatCompletionFunctionDefinition> = registry
-            .values()
-            .map(|tool| ChatCompletionFunctionDefinition {
-                name: tool.name().to_string(),
-                description: Some(tool.description().to_string()),
-                parameters: Some(tool.function_definition()),
-            })
-            .collect();
-
-        let mut should_continue = true;
-
-        while should_continue {
-            should_continue = false;
-
-            // Clean old function outputs from history
-            if history.len() > HIST_CUTOFF {
-                for i in 0..history.len().saturating_sub(HIST_CUTOFF) {
-                    if let ChatCompletionMessageRole::Function = history[i].role {
-                        history[i].content = Some(String::from("[cleaned from history]"));
-                    }
-                }
+    /// Runs the turn started by the last user message without a UI.
+    /// `capture_output` only applies to the text format.
+    pub async fn chat_headless(
+        &self,
+        options: &HeadlessOptions,
+        capture_output: bool,
+    ) -> (String, StopReason) {
+        let (events_tx, events) = event_channel();
+        let run = self.engine.run(self.messages.clone(), events_tx);
+
+        let (output, reason) = match options.output_format {
+            OutputFormat::Text => {
+                let (reason, output) = tokio::join!(
+                    run,
+                    print_text_events(events, &options.approver, capture_output)
+                );
+                (output, reason)
             }
-
-            let request = ChatCompletionRequest {
-                model: String::from(MODEL_NAME),
-                messages: history.clone(),
-                functions: if functions.is_empty() {
-                    None
-                } else {
-                    Some(functions.clone())
-                },
-            };
-
-            let url = format!("{}/chat/completions", self.base_url);
-
-            let chat_result = post_request_with_token_count(
-                &self.client,
-                &url,
-                &self.api_key,
-                request,
-                None,
-                self.token_counter.clone(),
-            )
-            .await;
-
-            if let Ok(chat_response) = chat_result {
-                let choice = chat_response.choices.first().unwrap();
-                let assistant_message = &choice.message;
-
-                // Add assistant message to history
-                history.push(ChatCompletionMessage {
-                    role: ChatCompletionMessageRole::Assistant,
-                    content: assistant_message.content.clone(),
-                    name: None,
-                    function_call: assistant_message.function_call.clone(),
-                    tool_call_id: None,
-                    tool_calls: None,
-                });
-
-                // Print or capture assistant response
-                if let Some(content) = &assistant_message.content {
-                    if capture_output {
-                        output_buffer.push(content.clone());
-                    } else {
-                    }
-                }
-
-                // Handle function call if present
-                if let Some(function_call) = &assistant_message.function_call {
-                    if !capture_output {
-                        println!("Handling function call: {}", function_call.name);
-                    }
-                    let function_call_result =
-                        handle_tool_call(function_call, None, is_headless).await;
-                    match function_call_result {
-                        ToolCallResult::Success(msg) => {
-                            history.push(msg);
-                            should_continue = true;
-                        }
-                        ToolCallResult::Cancelled => break,
-                        ToolCallResult::Error(err) => {
-                            let error_msg = format!("Error occurred in tool call: {}", err);
-                            if capture_output {
-                                output_buffer.push(error_msg);
-                            } else {
-                                eprintln!("Error occurred in tool call: {}", err);
-                            }
-                            break;
-                        }
-                    }
-                }
-            } else if let Err(req_err) = chat_result {
-                let error_msg = format!("Request Error: {req_err}");
-                if capture_output {
-                    output_buffer.push(error_msg);
-                } else {
-                    eprintln!("Request Error: {req_err}");
-                }
+            OutputFormat::Json | OutputFormat::StreamJson => {
+                let token_counter = self.token_counter();
+                let session_id = self.session_id();
+                let (reason, ()) = tokio::join!(
+                    run,
+                    print_json_events(events, options, &token_counter, &session_id)
+                );
+                (String::new(), reason)
             }
-        }
+        };
 
-        if capture_output {
-            output_buffer.join("\n")
-        } else {
-            String::new()
+        if let Err(err) = self.save_session() {
+            eprintln!("{}", err);
         }
+        (output, reason)
     }
 
     fn add_assistant_message_with_update_ui(
@@ -313,37 +296,53 @@ impl Minerve {
             role: ChatCompletionMessageRole::Assistant,
             content: Some(message_content),
             name: None,
-            function_call: None,
             tool_call_id: None,
             tool_calls: None,
         });
 
-        let ui_messages = msgs
-            .iter()
-            .map(|msg| {
-                let role = match msg.role {
-                    ChatCompletionMessageRole::System => "system".to_string(),
-                    ChatCompletionMessageRole::User => "user".to_string(),
-                    ChatCompletionMessageRole::Assistant => "minerve".to_string(),
-                    ChatCompletionMessageRole::Function => msg
-                        .tool_call_id
-                        .clone()
-                        .unwrap_or(String::from("unknown function call")),
-                };
-                (role, msg.content.clone().unwrap_or_default())
-            })
-            .collect();
+        let ui_messages = to_ui_messages(&msgs);
 
         let request_status = false;
         update_chat_ui(
             cb_sink.clone(),
             ui_messages,
             request_status,
-            self.token_counter.clone(),
+            self.token_counter(),
+            self.model(),
+        );
+    }
+
+    /// Shows the whole conversation, e.g. after resuming a session.
+    pub fn show_conversation(&self, cb_sink: &cursive::CbSink) {
+        let ui_messages = to_ui_messages(&self.messages.lock().unwrap());
+        update_chat_ui(
+            cb_sink.clone(),
+            ui_messages,
+            self.request_in_flight.load(Ordering::SeqCst),
+            self.token_counter(),
+            self.model(),
+        );
+    }
+
+    fn refresh_chat_ui(&self, cb_sink: &cursive::CbSink) {
+        let ui_messages = to_ui_messages(&self.messages.lock().unwrap());
+
+        let request_status = true;
+        update_chat_ui(
+            cb_sink.clone(),
+            ui_messages,
+            request_status,
+            self.token_counter(),
+            self.model(),
         );
     }
 
-    pub fn new() -> Self {
+    /// Creates a session; `model` overrides `MINERVE_MODEL` and the provider default.
+    pub fn new(
+        model: Option<String>,
+        config: Config,
+        tools: ToolRegistry,
+    ) -> Result<Self, MinerveError> {
         if let Some(home_dir) = dirs::home_dir() {
             let dotenv_path = home_dir.join(".env");
             if dotenv_path.exists() {
@@ -351,273 +350,641 @@ impl Minerve {
             }
         }
 
-        let api_key = env::var("OPENAI_API_KEY").expect("OPENAI_API_KEY must be set");
-        let base_url =
-            env::var("OPENAI_BASE_URL").unwrap_or_else(|_| "https://api.openai.com/v1".into());
+        let model = model.or_else(|| env::var("MINERVE_MODEL").ok());
+
+        // Replayed runs are not real spending, so keep them out of the ledger
+        let token_counter = match UsageLedger::open_default() {
+            Some(ledger) if env::var("MINERVE_REPLAY").is_err() => {
+                TokenCounter::with_ledger(ledger)
+            }
+            _ => TokenCounter::new(),
+        };
+
+        Ok(Self {
+            sessions: SessionStore::open_default(),
+            ..Self::with_provider(
+                provider_from_env()?,
+                model,
+                Arc::new(token_counter),
+                config,
+                tools,
+            )
+        })
+    }
+
+    /// Creates a session on an explicit provider, e.g. a `ReplayProvider` to
+    /// run the agent loop offline. The session is not saved to disk.
+    pub fn with_provider(
+        provider: Arc<dyn LlmProvider>,
+        model: Option<String>,
+        token_counter: Arc<TokenCounter>,
+        config: Config,
+        tools: ToolRegistry,
+    ) -> Self {
+        let model = model.unwrap_or_else(|| provider.default_model().to_string());
 
         let system_message = ChatCompletionMessage {
             role: ChatCompletionMessageRole::System,
             content: Some(get_system_prompt()),
             name: None,
-            function_call: None,
             tool_call_id: None,
             tool_calls: None,
         };
 
         Self {
             messages: Arc::new(Mutex::new(vec![system_message])),
-            client: Client::new(),
-            api_key,
-            base_url,
+            session: Mutex::new(ActiveSession {
+                session: Session::new(model.clone(), Vec::new()),
+                counted_at_start: SessionUsage::counted_by(&token_counter),
+            }),
+            sessions: None,
+            engine: Arc::new(AgentEngine::new(
+                provider,
+                model,
+                token_counter,
+                ContextManager::from_env(),
+                TurnLimits::from_env(),
+                config,
+                Arc::new(tools),
+            )),
+            current_task: Arc::new(Mutex::new(None)),
             request_in_flight: Arc::new(AtomicBool::new(false)),
-            token_counter: Arc::new(TokenCounter::new()),
         }
     }
 
-    pub fn chat_with_arc(
-        self: Arc<Self>,
-        user_input: String,
-        cb_sink: cursive::CbSink,
-        is_headless: bool,
-    ) {
-        use std::sync::atomic::Ordering;
+    pub fn session_id(&self) -> String {
+        self.session.lock().unwrap().session.id.clone()
+    }
+
+    /// Writes the conversation to `~/.minerve/sessions/<id>.json`, once it
+    /// has a user message.
+    pub fn save_session(&self) -> Result<(), MinerveError> {
+        let Some(sessions) = &self.sessions else {
+            return Ok(());
+        };
+
+        let active = self.session.lock().unwrap();
+        let mut session = Session {
+            messages: self.messages.lock().unwrap().clone(),
+            ..active.session.clone()
+        };
+        if !session.has_user_message() {
+            return Ok(());
+        }
+
+        session.model = self.model();
+        session.updated_at = chrono::Local::now();
+        session.usage = session.usage.plus_between(
+            active.counted_at_start,
+            SessionUsage::counted_by(&self.token_counter()),
+        );
+        sessions.save(&session)
+    }
+
+    /// Continues a saved session: its history replaces the current one and
+    /// later saves go to its file.
+    pub fn resume(&self, mut session: Session) {
+        *self.messages.lock().unwrap() = std::mem::take(&mut session.messages);
+        self.engine.set_mode(AgentMode::Normal);
+        self.engine.set_plan(None);
+        *self.session.lock().unwrap() = ActiveSession {
+            session,
+            counted_at_start: SessionUsage::counted_by(&self.token_counter()),
+        };
+    }
+
+    pub fn model(&self) -> String {
+        self.engine.model()
+    }
+
+    pub fn set_model(&self, model: String) {
+        self.engine.set_model(model);
+    }
+
+    pub fn token_counter(&self) -> Arc<TokenCounter> {
+        self.engine.token_counter()
+    }
+
+    /// Aborts the running turn, which drops the HTTP request and kills child
+    /// processes started by tools, then records that the user interrupted.
+    ///
+    /// Returns `false` when there was nothing to cancel.
+    pub fn cancel(self: &Arc<Self>, cb_sink: &cursive::CbSink) -> bool {
+        if !self.request_in_flight.load(Ordering::SeqCst) {
+            return false;
+        }
+        // Already being cancelled
+        let Some(task) = self.current_task.lock().unwrap().take() else {
+            return true;
+        };
+        task.abort();
+
+        let _ = cb_sink.send(Box::new(|s| {
+            if let Some(position) = s.screen_mut().find_layer_from_name(SHELL_CONFIRM_DIALOG) {
+                s.screen_mut().remove_layer(position);
+            }
+        }));
+
+        let self_clone = self.clone();
+        let cb_sink = cb_sink.clone();
+        crate::get_global_runtime().spawn(async move {
+            // The turn can still add messages until it has stopped
+            let _ = task.await;
+
+            answer_dangling_tool_calls(&mut self_clone.messages.lock().unwrap());
+            self_clone.add_assistant_message_with_update_ui(
+                &self_clone.messages,
+                String::from(INTERRUPTED_NOTE),
+                &cb_sink,
+            );
+            if let Err(err) = self_clone.save_session() {
+                show_status(&cb_sink, err.to_string());
+            }
+            self_clone.request_in_flight.store(false, Ordering::SeqCst);
+        });
+
+        true
+    }
+
+    /// Shows the events of a turn in the chat view until the turn is done.
+    async fn show_events(self: &Arc<Self>, mut events: EventReceiver, cb_sink: &cursive::CbSink) {
+        let mut is_first_delta = true;
+
+        while let Some(event) = events.recv().await {
+            match event {
+                AgentEvent::TextDelta(delta) => {
+                    append_chat_delta(cb_sink.clone(), delta, is_first_delta);
+                    is_first_delta = false;
+                }
+                AgentEvent::AssistantMessage(_) => {
+                    is_first_delta = true;
+                    self.refresh_chat_ui(cb_sink);
+                }
+                AgentEvent::ToolFinished { call, output } => {
+                    is_first_delta = true;
+                    self.refresh_chat_ui(cb_sink);
+                    if let Some(outcome) = tool_outcome(&call.function.name, &output) {
+                        show_status(cb_sink, outcome);
+                    }
+                }
+                AgentEvent::Status(message) => show_working_status(cb_sink, message),
+                AgentEvent::Retrying {
+                    reason,
+                    attempt,
+                    delay,
+                } => show_retry_countdown(cb_sink, &reason, attempt, delay).await,
+                AgentEvent::ToolStarted(call) => {
+                    show_working_status(cb_sink, format!("Running tool: {}", call.function.name))
+                }
+                AgentEvent::ApprovalRequested { command, respond } => {
+                    show_approval_dialog(cb_sink, command, respond)
+                }
+                AgentEvent::Usage { .. } => {
+                    update_status_line(cb_sink.clone(), self.token_counter(), self.model())
+                }
+                AgentEvent::PlanUpdated(plan) => show_plan_progress(cb_sink, &plan),
+                AgentEvent::VerificationFinished {
+                    command, passed, ..
+                } => {
+                    let outcome = if passed { "passed" } else { "failed" };
+                    show_status(cb_sink, format!("Verification {}: {}", outcome, command));
+                }
+                AgentEvent::Error(err) => {
+                    let error_msg = format!("Request Error: {}", err);
+                    self.add_assistant_message_with_update_ui(&self.messages, error_msg, cb_sink);
+                }
+                AgentEvent::Done(reason) => {
+                    self.request_in_flight.store(false, Ordering::SeqCst);
+                    show_working_status(cb_sink, String::new());
+                    if let Err(err) = self.save_session() {
+                        show_status(cb_sink, err.to_string());
+                    }
+                    match reason {
+                        StopReason::LimitReached(limit) => self.offer_to_continue(cb_sink, limit),
+                        StopReason::Completed => self.after_completed_turn(cb_sink),
+                        StopReason::Failed => {}
+                    }
+                    break;
+                }
+            }
+        }
+    }
+
+    fn after_completed_turn(self: &Arc<Self>, cb_sink: &cursive::CbSink) {
+        match self.engine.mode() {
+            AgentMode::Planning => self.review_plan(cb_sink),
+            AgentMode::Executing => {
+                if self.engine.plan().is_some_and(|plan| plan.is_complete()) {
+                    self.engine.set_mode(AgentMode::Normal);
+                    show_status(cb_sink, String::from("All steps of the plan are done."));
+                }
+            }
+            AgentMode::Normal | AgentMode::Delegated => {}
+        }
+    }
+
+    /// Lists the saved sessions, most recent first, to switch to one of them.
+    pub fn show_session_picker(self: Arc<Self>, cb_sink: &cursive::CbSink) {
+        use cursive::traits::{Resizable, Scrollable};
+        use cursive::views::{Dialog, SelectView};
+
+        if self.request_in_flight.load(Ordering::SeqCst) {
+            show_status(
+                cb_sink,
+                String::from("Wait for the current turn to finish before switching sessions."),
+            );
+            return;
+        }
+
+        let sessions = self
+            .sessions
+            .as_ref()
+            .map(|sessions| sessions.list())
+            .unwrap_or_default();
+        if sessions.is_empty() {
+            show_status(cb_sink, String::from("No saved sessions yet."));
+            return;
+        }
+
+        let cwd = current_dir();
+        let cb_sink = cb_sink.clone();
+        let _ = cb_sink.clone().send(Box::new(move |s| {
+            let mut select = SelectView::new();
+            for session in sessions {
+                let place = if session.cwd == cwd {
+                    String::new()
+                } else {
+                    format!("  ({})", session.cwd)
+                };
+                let label = format!(
+                    "{}  {}{}",
+                    session.updated_at.format("%Y-%m-%d %H:%M"),
+                    session.title(),
+                    place
+                );
+                select.add_item(label, session);
+            }
+            select.set_on_submit(move |s, session: &Session| {
+                s.pop_layer();
+                self.switch_session(session.clone(), &cb_sink);
+            });
+
+            s.add_layer(
+                Dialog::around(select.scrollable().max_height(20))
+                    .title("Sessions")
+                    .dismiss_button("Cancel"),
+            );
+        }));
+    }
+
+    fn switch_session(&self, session: Session, cb_sink: &cursive::CbSink) {
+        if let Err(err) = self.save_session() {
+            show_status(cb_sink, err.to_string());
+            return;
+        }
+
+        let id = session.id.clone();
+        self.set_model(session.model.clone());
+        self.resume(session);
+
+        clear_plan_panel(cb_sink);
+        self.show_conversation(cb_sink);
+        show_status(cb_sink, format!("Resumed session {}", id));
+    }
+
+    /// Removes the last user turn and everything after it, and puts its
+    /// prompt back in the input. Returns what to tell the user.
+    pub fn undo_last_turn(&self, cb_sink: &cursive::CbSink) -> String {
+        if self.request_in_flight.load(Ordering::SeqCst) {
+            return String::from("Wait for the current turn to finish before undoing it.");
+        }
+
+        let Some(prompt) = remove_last_turn(&mut self.messages.lock().unwrap()) else {
+            return String::from("Nothing to undo.");
+        };
+        self.engine.set_mode(AgentMode::Normal);
+        self.engine.set_plan(None);
+        clear_plan_panel(cb_sink);
+        self.show_conversation(cb_sink);
+        put_in_input(cb_sink, prompt);
+
+        match self.save_session() {
+            Ok(()) => String::from("Removed the last turn; its prompt is back in the input."),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    /// Lists the user's prompts, latest first, to fork the conversation
+    /// before one of them.
+    pub fn show_fork_picker(self: Arc<Self>, cb_sink: &cursive::CbSink) {
+        use cursive::traits::{Resizable, Scrollable};
+        use cursive::views::{Dialog, SelectView};
+
+        if self.request_in_flight.load(Ordering::SeqCst) {
+            show_status(
+                cb_sink,
+                String::from("Wait for the current turn to finish before forking."),
+            );
+            return;
+        }
+
+        let prompts: Vec<(usize, String)> = self
+            .messages
+            .lock()
+            .unwrap()
+            .iter()
+            .enumerate()
+            .filter(|(_, msg)| msg.is_user_prompt())
+            .map(|(index, msg)| (index, msg.content.clone().unwrap_or_default()))
+            .collect();
+        if prompts.is_empty() {
+            show_status(cb_sink, String::from("Nothing to fork yet."));
+            return;
+        }
 
         let cb_sink = cb_sink.clone();
+        let _ = cb_sink.clone().send(Box::new(move |s| {
+            let mut select = SelectView::new();
+            for (number, (index, prompt)) in prompts.into_iter().enumerate().rev() {
+                select.add_item(format!("{}. {}", number + 1, summary_line(&prompt)), index);
+            }
+            select.set_on_submit(move |s, index: &usize| {
+                s.pop_layer();
+                self.fork_before(*index, &cb_sink);
+            });
+
+            s.add_layer(
+                Dialog::around(select.scrollable().max_height(20))
+                    .title("Fork before which prompt?")
+                    .dismiss_button("Cancel"),
+            );
+        }));
+    }
+
+    /// Continues in a new session with the conversation before the user
+    /// message at `index`. The current session stays saved as it was, and
+    /// the prompt goes back to the input to be edited.
+    fn fork_before(&self, index: usize, cb_sink: &cursive::CbSink) {
+        if let Err(err) = self.save_session() {
+            show_status(cb_sink, err.to_string());
+            return;
+        }
+
+        let (messages, prompt) = {
+            let messages = self.messages.lock().unwrap();
+            let prompt = messages[index].content.clone().unwrap_or_default();
+            (messages[..turn_start(&messages, index)].to_vec(), prompt)
+        };
+
+        let mut session = Session::new(self.model(), messages);
+        session.forked_from = Some(self.session_id());
+        let id = session.id.clone();
+        self.resume(session);
+
+        clear_plan_panel(cb_sink);
+        self.show_conversation(cb_sink);
+        put_in_input(cb_sink, prompt);
+        show_status(
+            cb_sink,
+            format!("Forked into session {}; edit the prompt and send it.", id),
+        );
+    }
+
+    /// Asks the model for a plan, using only read-only tools. Returns false if
+    /// a turn is already running.
+    pub fn start_planning(self: Arc<Self>, task: String, cb_sink: cursive::CbSink) -> bool {
+        if !self.claim_turn(&cb_sink) {
+            return false;
+        }
+        self.engine.set_mode(AgentMode::Planning);
+        self.engine.set_plan(None);
+        clear_plan_panel(&cb_sink);
+
+        self.messages.lock().unwrap().push(ChatCompletionMessage {
+            role: ChatCompletionMessageRole::System,
+            content: Some(String::from(PLANNING_PROMPT)),
+            name: None,
+            tool_call_id: None,
+            tool_calls: None,
+        });
+        self.push_user_message(task);
+        self.spawn_turn(cb_sink);
+        true
+    }
+
+    /// Lets the user edit the proposed plan, then accept or reject it.
+    fn review_plan(self: &Arc<Self>, cb_sink: &cursive::CbSink) {
+        use cursive::traits::{Nameable, Resizable};
+        use cursive::views::{Dialog, TextArea};
+
+        let answer = self
+            .messages
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|msg| matches!(msg.role, ChatCompletionMessageRole::Assistant))
+            .and_then(|msg| msg.content.clone())
+            .unwrap_or_default();
+
+        let Some(plan) = Plan::parse(&answer) else {
+            self.end_planning();
+            show_status(
+                cb_sink,
+                String::from("No numbered plan in the answer; plan mode ended."),
+            );
+            return;
+        };
+
+        let minerve_accept = self.clone();
+        let minerve_reject = self.clone();
+        let _ = cb_sink.send(Box::new(move |s| {
+            s.add_layer(
+                Dialog::around(
+                    TextArea::new()
+                        .content(plan.to_numbered_list())
+                        .with_name(PLAN_EDITOR)
+                        .min_width(60),
+                )
+                .title("Review the plan")
+                .button("Accept", move |s| {
+                    let edited = s
+                        .call_on_name(PLAN_EDITOR, |view: &mut TextArea| {
+                            view.get_content().to_string()
+                        })
+                        .unwrap_or_default();
+                    match Plan::parse(&edited) {
+                        Some(plan) => {
+                            s.pop_layer();
+                            minerve_accept
+                                .clone()
+                                .execute_plan(plan, s.cb_sink().clone());
+                        }
+                        None => {
+                            s.call_on_name("status", |view: &mut TextView| {
+                                view.set_content("The plan needs at least one numbered step.")
+                            });
+                        }
+                    }
+                })
+                .button("Reject", move |s| {
+                    s.pop_layer();
+                    minerve_reject.end_planning();
+                    s.call_on_name("status", |view: &mut TextView| {
+                        view.set_content("Plan rejected.")
+                    });
+                }),
+            );
+        }));
+    }
+
+    /// Leaves plan mode without a plan, telling the model it may act again.
+    fn end_planning(&self) {
+        self.engine.set_mode(AgentMode::Normal);
+        self.messages.lock().unwrap().push(ChatCompletionMessage {
+            role: ChatCompletionMessageRole::System,
+            content: Some(String::from(PLANNING_ENDED_NOTE)),
+            name: None,
+            tool_call_id: None,
+            tool_calls: None,
+        });
+    }
 
-        self.request_in_flight.store(true, Ordering::SeqCst);
+    /// Unlocks every tool and asks the model to carry out the accepted plan.
+    fn execute_plan(self: Arc<Self>, plan: Plan, cb_sink: cursive::CbSink) {
+        if !self.claim_turn(&cb_sink) {
+            return;
+        }
+        show_plan_progress(&cb_sink, &plan);
+        let prompt = plan.execution_prompt();
+        self.engine.set_plan(Some(plan));
+        self.engine.set_mode(AgentMode::Executing);
+        self.push_user_message(prompt);
+        self.spawn_turn(cb_sink);
+    }
+
+    /// Tells the user which limit stopped the turn and lets them resume it.
+    fn offer_to_continue(self: &Arc<Self>, cb_sink: &cursive::CbSink, limit: TurnLimit) {
+        use cursive::views::Dialog;
+
+        let minerve = self.clone();
+        let _ = cb_sink.send(Box::new(move |s| {
+            s.add_layer(
+                Dialog::text(format!(
+                    "Stopped after reaching the {}.\nLet Minerve continue?",
+                    limit
+                ))
+                .button("Continue", move |s| {
+                    s.pop_layer();
+                    minerve.clone().run_turn(s.cb_sink().clone());
+                })
+                .button("Stop", |s| {
+                    s.pop_layer();
+                }),
+            );
+        }));
+    }
 
+    /// Sends the user's message and runs the agent on it. Returns false, and
+    /// leaves the history alone, if a turn is already running.
+    pub fn chat_with_arc(self: Arc<Self>, user_input: String, cb_sink: cursive::CbSink) -> bool {
+        if !self.claim_turn(&cb_sink) {
+            return false;
+        }
+        self.push_user_message(user_input);
+        self.spawn_turn(cb_sink);
+        true
+    }
+
+    fn push_user_message(&self, user_input: String) {
         let mut msgs = self.messages.lock().unwrap();
 
         let user_message = ChatCompletionMessage {
             role: ChatCompletionMessageRole::User,
             content: Some(user_input.clone()),
             name: None,
-            function_call: None,
             tool_call_id: None,
             tool_calls: None,
         };
         msgs.push(user_message);
+    }
 
-        let ui_messages = msgs
-            .iter()
-            .map(|msg| {
-                let role = match msg.role {
-                    ChatCompletionMessageRole::System => "system".to_string(),
-                    ChatCompletionMessageRole::User => "user".to_string(),
-                    ChatCompletionMessageRole::Assistant => "minerve".to_string(),
-                    ChatCompletionMessageRole::Function => msg
-                        .tool_call_id
-                        .clone()
-                        .unwrap_or(String::from("unknown function call")),
-                };
-                (role, msg.content.clone().unwrap_or_default())
-            })
-            .collect();
-
-        let request_status = false;
-        update_chat_ui(
-            cb_sink.clone(),
-            ui_messages,
-            request_status,
-            self.token_counter.clone(),
-        );
-
-        // Show working indicator
-        cb_sink
-            .send(Box::new(|s| {
-                if let Some(mut view) = s.find_name::<ResizedView<TextView>>("working_textview") {
-                    view.get_inner_mut().set_content("working...");
-                } else {
-                    panic!("working_textview view not found");
-                }
-            }))
-            .unwrap();
+    /// Marks a turn as running, unless one already is: two runs on the same
+    /// history would interleave their messages.
+    fn claim_turn(&self, cb_sink: &cursive::CbSink) -> bool {
+        let claimed = self
+            .request_in_flight
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok();
+        if !claimed {
+            show_status(
+                cb_sink,
+                String::from("Wait for the current turn to finish before sending another message."),
+            );
+        }
+        claimed
+    }
 
-        let messages = msgs.clone();
-        drop(msgs);
+    /// Continues a turn stopped by a limit.
+    pub fn run_turn(self: Arc<Self>, cb_sink: cursive::CbSink) {
+        if self.claim_turn(&cb_sink) {
+            self.spawn_turn(cb_sink);
+        }
+    }
 
-        let client = self.client.clone();
-        let api_key = self.api_key.clone();
-        let base_url = self.base_url.clone();
-        let messages_clone = self.messages.clone();
-        let request_in_flight = self.request_in_flight.clone();
-        let token_counter = self.token_counter.clone();
-        let cb_sink_clone = cb_sink.clone();
+    /// Runs the agent on the current history, once the turn is claimed.
+    fn spawn_turn(self: Arc<Self>, cb_sink: cursive::CbSink) {
+        self.refresh_chat_ui(&cb_sink);
 
-        crate::get_global_runtime().spawn({
+        let task = crate::get_global_runtime().spawn({
             let self_clone = self.clone();
             async move {
-                let mut history: Vec<ChatCompletionMessage> = messages;
-                let registry = get_tool_registry();
-                let functions: Vec<ChatCompletionFunctionDefinition> = registry
-                    .values()
-                    .map(|tool| ChatCompletionFunctionDefinition {
-                        name: tool.name().to_string(),
-                        description: Some(tool.description().to_string()),
-                        parameters: Some(tool.function_definition()),
-                    })
-                    .collect();
-
-                let mut should_continue = true;
-
-                while should_continue {
-                    should_continue = false;
-
-                    // Show working indicator at start of each loop iteration
-                    cb_sink_clone
-                        .send(Box::new(|s| {
-                            if let Some(mut view) =
-                                s.find_name::<ResizedView<TextView>>("working_textview")
-                            {
-                                view.get_inner_mut().set_content("working...");
-                            } else {
-                                panic!("working_textview view not found");
-                            }
-                        }))
-                        .unwrap();
-
-                    let history_len = history.len();
-                    let mut cleaned_history = history.clone();
-                    if history_len > 30 {
-                        for i in 0..history_len - 30 {
-                            if let ChatCompletionMessageRole::Function = cleaned_history[i].role {
-                                cleaned_history[i].content =
-                                    Some("[cleaned from history]".to_string());
-                            }
-                        }
-                    }
+                let (events_tx, events) = event_channel();
+
+                tokio::join!(
+                    self_clone
+                        .engine
+                        .run(self_clone.messages.clone(), events_tx),
+                    self_clone.show_events(events, &cb_sink)
+                );
+            }
+        });
 
-                    let request = ChatCompletionRequest {
-                        model: String::from(MODEL_NAME),
-                        messages: cleaned_history,
-                        functions: if functions.is_empty() {
-                            None
-                        } else {
-                            Some(functions.clone())
-                        },
-                    };
-
-                    let url = format!("{}/chat/completions", base_url);
-
-                    let chat_result = post_request_with_token_count(
-                        &client,
-                        &url,
-                        &api_key,
-                        request,
-                        Some(&cb_sink_clone),
-                        token_counter.clone(),
-                    )
-                    .await;
-
-                    match chat_result {
-                        Ok(response) => {
-                            let choice = response.choices.first().unwrap();
-                            let assistant_message = &choice.message;
-
-                            history.push(ChatCompletionMessage {
-                                role: ChatCompletionMessageRole::Assistant,
-                                content: assistant_message.content.clone(),
-                                name: None,
-                                function_call: assistant_message.function_call.clone(),
-                                tool_call_id: None,
-                                tool_calls: None,
-                            });
+        *self.current_task.lock().unwrap() = Some(task);
+    }
+}
 
-                            if let Some(content) = &assistant_message.content {
-                                let mut msgs = messages_clone.lock().unwrap();
-                                msgs.push(ChatCompletionMessage {
-                                    role: ChatCompletionMessageRole::Assistant,
-                                    content: Some(content.clone()),
-                                    name: None,
-                                    function_call: None,
-                                    tool_call_id: None,
-                                    tool_calls: None,
-                                });
-                            }
-
-                            if let Some(function_call) = &assistant_message.function_call {
-                                let tool_call_result = handle_tool_call(
-                                    function_call,
-                                    Some(cb_sink_clone.clone()),
-                                    is_headless,
-                                )
-                                .await;
-
-                                match tool_call_result {
-                                    ToolCallResult::Cancelled => break,
-                                    ToolCallResult::Success(msg) => {
-                                        if msg.content.is_some() {
-                                            let mut msgs = messages_clone.lock().unwrap();
-                                            msgs.push(msg.clone());
-                                        }
-                                        history.push(msg);
-                                        should_continue = true;
-                                    }
-                                    ToolCallResult::Error(err) => {
-                                        let msg = format!("Error occurred in tool call: {}", err);
-                                        self_clone.add_assistant_message_with_update_ui(
-                                            &messages_clone,
-                                            msg,
-                                            &cb_sink_clone,
-                                        );
-                                        break;
-                                    }
-                                }
-                            }
-
-                            let ui_messages = messages_clone
-                                .lock()
-                                .unwrap()
-                                .iter()
-                                .map(|msg| {
-                                    let role = match msg.role {
-                                        ChatCompletionMessageRole::System => "system".to_string(),
-                                        ChatCompletionMessageRole::User => "user".to_string(),
-                                        ChatCompletionMessageRole::Assistant => {
-                                            "minerve".to_string()
-                                        }
-                                        ChatCompletionMessageRole::Function => msg
-                                            .tool_call_id
-                                            .clone()
-                                            .unwrap_or(String::from("unknown function call")),
-                                    };
-                                    (role, msg.content.clone().unwrap_or_default())
-                                })
-                                .collect();
-
-                            let request_status = false;
-                            update_chat_ui(
-                                cb_sink_clone.clone(),
-                                ui_messages,
-                                request_status,
-                                token_counter.clone(),
-                            );
-                        }
-                        Err(req_err) => {
-                            let error_msg = format!("Request Error: {}", req_err);
-                            self_clone.add_assistant_message_with_update_ui(
-                                &messages_clone,
-                                error_msg,
-                                &cb_sink_clone,
-                            );
-                            break;
-                        }
-                    }
-                }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::MINERVE_MESSAGE_NAME;
 
-                request_in_flight.store(false, Ordering::SeqCst);
-                cb_sink_clone
-                    .send(Box::new(|s| {
-                        if let Some(mut view) =
-                            s.find_name::<ResizedView<TextView>>("working_textview")
-                        {
-                            view.get_inner_mut().set_content("");
-                        } else {
-                            panic!("working_textview view not found");
-                        }
-                    }))
-                    .unwrap();
-            }
-        });
+    fn message(role: ChatCompletionMessageRole, content: &str) -> ChatCompletionMessage {
+        ChatCompletionMessage {
+            role,
+            content: Some(String::from(content)),
+            name: None,
+            tool_call_id: None,
+            tool_calls: None,
+        }
+    }
+
+    #[test]
+    fn undo_removes_the_verification_reports_with_their_turn() {
+        let mut report = message(ChatCompletionMessageRole::User, "cargo test failed");
+        report.name = Some(String::from(MINERVE_MESSAGE_NAME));
+        let mut messages = vec![
+            message(ChatCompletionMessageRole::System, "system"),
+            message(ChatCompletionMessageRole::User, "fix the bug"),
+            message(ChatCompletionMessageRole::Assistant, "done"),
+            report,
+            message(ChatCompletionMessageRole::Assistant, "fixed the test too"),
+        ];
+
+        assert_eq!(
+            remove_last_turn(&mut messages).as_deref(),
+            Some("fix the bug")
+        );
+        assert_eq!(messages.len(), 1);
     }
 }
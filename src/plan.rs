@@ -0,0 +1,97 @@
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Instructions sent before the task when the user asks for a plan first.
+pub const PLANNING_PROMPT: &str = "You are in planning mode. Investigate the codebase with the read-only tools available, but do not change anything. Then answer with a numbered plan, one step per line (\"1. ...\"), that you will carry out once the user accepts it.";
+
+/// Sent when the user rejects the plan, so that the model leaves planning mode.
+pub const PLANNING_ENDED_NOTE: &str =
+    "Planning mode is over and the plan was not accepted. All tools are available again.";
+
+/// One step of a plan and whether the model reported it done.
+#[derive(Debug, Clone)]
+pub struct PlanStep {
+    pub description: String,
+    pub done: bool,
+}
+
+/// A numbered plan proposed by the model and accepted, possibly edited, by the user.
+#[derive(Debug, Clone)]
+pub struct Plan {
+    pub steps: Vec<PlanStep>,
+}
+
+fn numbered_line() -> &'static Regex {
+    static NUMBERED_LINE: OnceLock<Regex> = OnceLock::new();
+    NUMBERED_LINE.get_or_init(|| Regex::new(r"^\s*\d+[.)]\s+(.+)$").unwrap())
+}
+
+impl Plan {
+    /// Reads the numbered lines (`1. ...` or `1) ...`) of `text`, ignoring
+    /// everything else. Returns `None` when there are none.
+    pub fn parse(text: &str) -> Option<Plan> {
+        let steps: Vec<PlanStep> = text
+            .lines()
+            .filter_map(|line| numbered_line().captures(line))
+            .map(|captures| PlanStep {
+                description: captures[1].trim().to_string(),
+                done: false,
+            })
+            .collect();
+
+        if steps.is_empty() {
+            None
+        } else {
+            Some(Plan { steps })
+        }
+    }
+
+    /// Marks the step with the given 1-based number as done.
+    pub fn complete_step(&mut self, number: usize) -> bool {
+        match number
+            .checked_sub(1)
+            .and_then(|index| self.steps.get_mut(index))
+        {
+            Some(step) => {
+                step.done = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.steps.iter().all(|step| step.done)
+    }
+
+    /// The steps as a numbered list, for editing and for the model.
+    pub fn to_numbered_list(&self) -> String {
+        self.steps
+            .iter()
+            .enumerate()
+            .map(|(index, step)| format!("{}. {}", index + 1, step.description))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The steps with check marks, for the progress panel.
+    pub fn progress(&self) -> String {
+        self.steps
+            .iter()
+            .enumerate()
+            .map(|(index, step)| {
+                let mark = if step.done { "[x]" } else { "[ ]" };
+                format!("{} {}. {}", mark, index + 1, step.description)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Message that starts the execution of the accepted plan.
+    pub fn execution_prompt(&self) -> String {
+        format!(
+            "The plan is accepted. Carry it out step by step, and call complete_plan_step with the step number after finishing each step:\n{}",
+            self.to_numbered_list()
+        )
+    }
+}
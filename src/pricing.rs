@@ -0,0 +1,53 @@
+/// Price in US dollars per million tokens.
+#[derive(Debug, Clone, Copy)]
+pub struct ModelPricing {
+    pub prompt_per_million: f64,
+    pub completion_per_million: f64,
+}
+
+impl ModelPricing {
+    pub fn cost(&self, prompt_tokens: u64, completion_tokens: u64) -> f64 {
+        (prompt_tokens as f64 * self.prompt_per_million
+            + completion_tokens as f64 * self.completion_per_million)
+            / 1_000_000.0
+    }
+}
+
+/// Known model prices, matched by prefix so that dated snapshots
+/// (e.g. `gpt-4o-2024-08-06`) resolve too. More specific prefixes come first.
+const PRICING_TABLE: &[(&str, ModelPricing)] = &[
+    ("gpt-4o-mini", pricing(0.15, 0.60)),
+    ("gpt-4o", pricing(2.50, 10.00)),
+    ("gpt-4.1-nano", pricing(0.10, 0.40)),
+    ("gpt-4.1-mini", pricing(0.40, 1.60)),
+    ("gpt-4.1", pricing(2.00, 8.00)),
+    ("o4-mini", pricing(1.10, 4.40)),
+    ("o3-mini", pricing(1.10, 4.40)),
+    ("o3", pricing(2.00, 8.00)),
+    ("claude-opus-4", pricing(15.00, 75.00)),
+    ("claude-sonnet-4", pricing(3.00, 15.00)),
+    ("claude-3-7-sonnet", pricing(3.00, 15.00)),
+    ("claude-3-5-sonnet", pricing(3.00, 15.00)),
+    ("claude-3-5-haiku", pricing(0.80, 4.00)),
+];
+
+const fn pricing(prompt_per_million: f64, completion_per_million: f64) -> ModelPricing {
+    ModelPricing {
+        prompt_per_million,
+        completion_per_million,
+    }
+}
+
+/// Returns `None` for models without a known price, such as local Ollama models.
+pub fn pricing_for(model: &str) -> Option<ModelPricing> {
+    PRICING_TABLE
+        .iter()
+        .find(|(prefix, _)| model.starts_with(prefix))
+        .map(|(_, pricing)| *pricing)
+}
+
+pub fn cost_for(model: &str, prompt_tokens: u64, completion_tokens: u64) -> f64 {
+    pricing_for(model)
+        .map(|pricing| pricing.cost(prompt_tokens, completion_tokens))
+        .unwrap_or(0.0)
+}
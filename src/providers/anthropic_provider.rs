@@ -0,0 +1,253 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{check_status, LlmProvider};
+use crate::chat::{
+    ChatCompletionChoice, ChatCompletionFunctionCall, ChatCompletionMessage,
+    ChatCompletionMessageRole, ChatCompletionRequest, ChatCompletionResponse,
+    ChatCompletionToolCall, Usage,
+};
+use crate::error::MinerveError;
+
+pub const ANTHROPIC_DEFAULT_MODEL: &str = "claude-sonnet-4-20250514";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+const MAX_TOKENS: u64 = 8192;
+
+/// Anthropic Messages API (`/v1/messages`).
+///
+/// Responses are not streamed; the whole text is passed to `on_delta` at once.
+pub struct AnthropicProvider {
+    client: Client,
+    api_key: String,
+    base_url: String,
+}
+
+#[derive(Debug, Serialize)]
+struct MessagesRequest {
+    model: String,
+    max_tokens: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    messages: Vec<Message>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<ToolDefinition>,
+}
+
+#[derive(Debug, Serialize)]
+struct Message {
+    role: &'static str,
+    content: Vec<ContentBlock>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Serialize)]
+struct ToolDefinition {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    input_schema: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessagesResponse {
+    content: Vec<ContentBlock>,
+    usage: Option<MessagesUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessagesUsage {
+    input_tokens: u64,
+    output_tokens: u64,
+}
+
+impl AnthropicProvider {
+    pub fn new(api_key: String, base_url: String) -> Self {
+        Self {
+            client: Client::new(),
+            api_key,
+            base_url,
+        }
+    }
+
+    fn to_messages_request(request: ChatCompletionRequest) -> MessagesRequest {
+        let mut system_prompts = Vec::new();
+        let mut messages: Vec<Message> = Vec::new();
+
+        for message in request.messages {
+            let (role, blocks) = match message.role {
+                ChatCompletionMessageRole::System => {
+                    system_prompts.extend(message.content);
+                    continue;
+                }
+                ChatCompletionMessageRole::User => (
+                    "user",
+                    message
+                        .content
+                        .map(|text| vec![ContentBlock::Text { text }])
+                        .unwrap_or_default(),
+                ),
+                ChatCompletionMessageRole::Assistant => {
+                    let mut blocks = Vec::new();
+                    if let Some(text) = message.content.filter(|t| !t.is_empty()) {
+                        blocks.push(ContentBlock::Text { text });
+                    }
+                    for tool_call in message.tool_calls.unwrap_or_default() {
+                        blocks.push(ContentBlock::ToolUse {
+                            id: tool_call.id,
+                            name: tool_call.function.name,
+                            input: serde_json::from_str(&tool_call.function.arguments)
+                                .unwrap_or_else(|_| Value::Object(Default::default())),
+                        });
+                    }
+                    ("assistant", blocks)
+                }
+                ChatCompletionMessageRole::Tool => (
+                    "user",
+                    vec![ContentBlock::ToolResult {
+                        tool_use_id: message.tool_call_id.unwrap_or_default(),
+                        content: message.content.unwrap_or_default(),
+                    }],
+                ),
+            };
+
+            if blocks.is_empty() {
+                continue;
+            }
+
+            // The Messages API expects alternating roles, so merge consecutive
+            // messages such as several tool results
+            match messages.last_mut() {
+                Some(last) if last.role == role => last.content.extend(blocks),
+                _ => messages.push(Message {
+                    role,
+                    content: blocks,
+                }),
+            }
+        }
+
+        let tools = request
+            .tools
+            .unwrap_or_default()
+            .into_iter()
+            .map(|tool| ToolDefinition {
+                name: tool.function.name,
+                description: tool.function.description,
+                input_schema: tool
+                    .function
+                    .parameters
+                    .unwrap_or_else(|| serde_json::json!({"type": "object"})),
+            })
+            .collect();
+
+        MessagesRequest {
+            model: request.model,
+            max_tokens: MAX_TOKENS,
+            system: if system_prompts.is_empty() {
+                None
+            } else {
+                Some(system_prompts.join("\n\n"))
+            },
+            messages,
+            tools,
+        }
+    }
+
+    fn to_chat_completion_response(response: MessagesResponse) -> ChatCompletionResponse {
+        let mut content = String::new();
+        let mut tool_calls = Vec::new();
+
+        for block in response.content {
+            match block {
+                ContentBlock::Text { text } => content.push_str(&text),
+                ContentBlock::ToolUse { id, name, input } => {
+                    tool_calls.push(ChatCompletionToolCall {
+                        id,
+                        kind: String::from("function"),
+                        function: ChatCompletionFunctionCall {
+                            name,
+                            arguments: input.to_string(),
+                        },
+                    })
+                }
+                ContentBlock::ToolResult { .. } | ContentBlock::Unknown => {}
+            }
+        }
+
+        ChatCompletionResponse {
+            choices: vec![ChatCompletionChoice {
+                message: ChatCompletionMessage {
+                    role: ChatCompletionMessageRole::Assistant,
+                    content: if content.is_empty() {
+                        None
+                    } else {
+                        Some(content)
+                    },
+                    name: None,
+                    tool_call_id: None,
+                    tool_calls: if tool_calls.is_empty() {
+                        None
+                    } else {
+                        Some(tool_calls)
+                    },
+                },
+            }],
+            usage: response.usage.map(|usage| Usage {
+                prompt_tokens: usage.input_tokens,
+                completion_tokens: usage.output_tokens,
+            }),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for AnthropicProvider {
+    fn default_model(&self) -> &'static str {
+        ANTHROPIC_DEFAULT_MODEL
+    }
+
+    async fn send(
+        &self,
+        request: ChatCompletionRequest,
+        on_delta: &mut (dyn FnMut(String) + Send),
+    ) -> Result<ChatCompletionResponse, MinerveError> {
+        let url = format!("{}/messages", self.base_url);
+
+        let response = self
+            .client
+            .post(url)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .header("Content-Type", "application/json")
+            .json(&Self::to_messages_request(request))
+            .send()
+            .await?;
+        let response: MessagesResponse = check_status(response).await?.json().await?;
+
+        let chat_response = Self::to_chat_completion_response(response);
+        if let Some(content) = &chat_response.choices[0].message.content {
+            on_delta(content.clone());
+        }
+
+        Ok(chat_response)
+    }
+}
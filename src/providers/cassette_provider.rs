@@ -0,0 +1,127 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::LlmProvider;
+use crate::chat::{ChatCompletionRequest, ChatCompletionResponse};
+use crate::error::MinerveError;
+
+/// One recorded request/response pair; a cassette file holds one per line.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: ChatCompletionRequest,
+    pub response: ChatCompletionResponse,
+}
+
+/// Forwards requests to another provider and appends every exchange to a cassette.
+pub struct RecordingProvider {
+    inner: Arc<dyn LlmProvider>,
+    path: PathBuf,
+}
+
+impl RecordingProvider {
+    pub fn new(inner: Arc<dyn LlmProvider>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            path: path.into(),
+        }
+    }
+
+    fn append(&self, interaction: &Interaction) -> std::io::Result<()> {
+        let line = serde_json::to_string(interaction)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", line)
+    }
+}
+
+#[async_trait]
+impl LlmProvider for RecordingProvider {
+    fn default_model(&self) -> &'static str {
+        self.inner.default_model()
+    }
+
+    async fn send(
+        &self,
+        request: ChatCompletionRequest,
+        on_delta: &mut (dyn FnMut(String) + Send),
+    ) -> Result<ChatCompletionResponse, MinerveError> {
+        let response = self.inner.send(request.clone(), on_delta).await?;
+
+        if let Err(err) = self.append(&Interaction {
+            request,
+            response: response.clone(),
+        }) {
+            eprintln!(
+                "Failed to record interaction to {}: {}",
+                self.path.display(),
+                err
+            );
+        }
+
+        Ok(response)
+    }
+}
+
+/// Serves the responses of a cassette in order, without any network access.
+pub struct ReplayProvider {
+    interactions: Mutex<VecDeque<Interaction>>,
+}
+
+impl ReplayProvider {
+    pub fn new(interactions: Vec<Interaction>) -> Self {
+        Self {
+            interactions: Mutex::new(interactions.into()),
+        }
+    }
+
+    pub fn from_file(path: &Path) -> std::io::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let interactions = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str::<Interaction>)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self::new(interactions))
+    }
+}
+
+#[async_trait]
+impl LlmProvider for ReplayProvider {
+    fn default_model(&self) -> &'static str {
+        "replay"
+    }
+
+    async fn send(
+        &self,
+        _request: ChatCompletionRequest,
+        on_delta: &mut (dyn FnMut(String) + Send),
+    ) -> Result<ChatCompletionResponse, MinerveError> {
+        let interaction = self
+            .interactions
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or_else(|| {
+                MinerveError::Replay(String::from("no recorded responses left in cassette"))
+            })?;
+
+        let content = interaction
+            .response
+            .choices
+            .first()
+            .and_then(|choice| choice.message.content.clone());
+        if let Some(content) = content {
+            on_delta(content);
+        }
+
+        Ok(interaction.response)
+    }
+}
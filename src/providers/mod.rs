@@ -0,0 +1,144 @@
+use async_trait::async_trait;
+use reqwest::Response;
+use std::env;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::chat::{ChatCompletionRequest, ChatCompletionResponse};
+use crate::error::MinerveError;
+
+pub mod anthropic_provider;
+pub mod cassette_provider;
+pub mod ollama_provider;
+pub mod openai_provider;
+
+use anthropic_provider::AnthropicProvider;
+use cassette_provider::{RecordingProvider, ReplayProvider};
+use ollama_provider::OllamaProvider;
+use openai_provider::OpenAiProvider;
+
+/// A chat completion backend.
+///
+/// Requests and responses use Minerve's OpenAI-shaped types from `chat.rs`;
+/// each provider translates them to and from its own wire format.
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    /// Model used when none is configured.
+    fn default_model(&self) -> &'static str;
+
+    /// Sends the request, passing assistant text to `on_delta` as it arrives.
+    async fn send(
+        &self,
+        request: ChatCompletionRequest,
+        on_delta: &mut (dyn FnMut(String) + Send),
+    ) -> Result<ChatCompletionResponse, MinerveError>;
+}
+
+/// Turns a non-success response into `MinerveError::Api`, keeping the
+/// provider's error message and any `Retry-After` delay.
+pub async fn check_status(response: Response) -> Result<Response, MinerveError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_retry_after);
+
+    let body = response.text().await.unwrap_or_default();
+
+    Err(MinerveError::Api {
+        status,
+        message: parse_error_message(&body),
+        retry_after,
+    })
+}
+
+/// `Retry-After` is either a number of seconds or an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    let seconds = (date.timestamp() - chrono::Utc::now().timestamp()).max(0);
+    Some(Duration::from_secs(seconds as u64))
+}
+
+/// Extracts the message from OpenAI (`{"error": {"message"}}`), Anthropic
+/// (`{"type": "error", "error": {"message"}}`) and Ollama (`{"error": "..."}`) error bodies.
+fn parse_error_message(body: &str) -> String {
+    let parsed = serde_json::from_str::<serde_json::Value>(body).ok();
+    let message = parsed.as_ref().and_then(|value| {
+        let error = value.get("error")?;
+        error
+            .get("message")
+            .and_then(|message| message.as_str())
+            .or_else(|| error.as_str())
+            .map(str::to_string)
+    });
+
+    message.unwrap_or_else(|| {
+        let body = body.trim();
+        if body.is_empty() {
+            String::from("(empty response body)")
+        } else {
+            crate::tools::utils::truncate(body.to_string(), 500)
+        }
+    })
+}
+
+/// Builds the provider selected by `MINERVE_PROVIDER` (openai, anthropic or ollama).
+///
+/// `MINERVE_RECORD=<path>` records every exchange to a cassette file, and
+/// `MINERVE_REPLAY=<path>` serves a recorded cassette instead of calling any API.
+pub fn provider_from_env() -> Result<Arc<dyn LlmProvider>, MinerveError> {
+    if let Ok(path) = env::var("MINERVE_REPLAY") {
+        let replay = ReplayProvider::from_file(Path::new(&path))
+            .map_err(|e| MinerveError::io(format!("Failed to load cassette {}", path), e))?;
+        return Ok(Arc::new(replay));
+    }
+
+    let provider = backend_from_env()?;
+
+    Ok(match env::var("MINERVE_RECORD") {
+        Ok(path) => Arc::new(RecordingProvider::new(provider, path)),
+        Err(_) => provider,
+    })
+}
+
+fn required_var(name: &str) -> Result<String, MinerveError> {
+    env::var(name).map_err(|_| MinerveError::Config(format!("{} must be set", name)))
+}
+
+fn backend_from_env() -> Result<Arc<dyn LlmProvider>, MinerveError> {
+    let provider = env::var("MINERVE_PROVIDER").unwrap_or_else(|_| "openai".into());
+
+    match provider.to_lowercase().as_str() {
+        "anthropic" => {
+            let api_key = required_var("ANTHROPIC_API_KEY")?;
+            let base_url = env::var("ANTHROPIC_BASE_URL")
+                .unwrap_or_else(|_| "https://api.anthropic.com/v1".into());
+            Ok(Arc::new(AnthropicProvider::new(api_key, base_url)))
+        }
+        "ollama" => {
+            let base_url =
+                env::var("OLLAMA_BASE_URL").unwrap_or_else(|_| "http://localhost:11434".into());
+            Ok(Arc::new(OllamaProvider::new(base_url)))
+        }
+        "openai" => {
+            let api_key = required_var("OPENAI_API_KEY")?;
+            let base_url =
+                env::var("OPENAI_BASE_URL").unwrap_or_else(|_| "https://api.openai.com/v1".into());
+            Ok(Arc::new(OpenAiProvider::new(api_key, base_url)))
+        }
+        other => Err(MinerveError::Config(format!(
+            "Unknown MINERVE_PROVIDER '{}', expected openai, anthropic or ollama",
+            other
+        ))),
+    }
+}
@@ -0,0 +1,180 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{check_status, LlmProvider};
+use crate::chat::{
+    ChatCompletionChoice, ChatCompletionFunctionCall, ChatCompletionMessage,
+    ChatCompletionMessageRole, ChatCompletionRequest, ChatCompletionResponse,
+    ChatCompletionToolCall, ChatCompletionToolDefinition, Usage,
+};
+use crate::error::MinerveError;
+
+pub const OLLAMA_DEFAULT_MODEL: &str = "llama3.1";
+
+/// Local Ollama server (`/api/chat`).
+///
+/// Responses are not streamed; the whole text is passed to `on_delta` at once.
+pub struct OllamaProvider {
+    client: Client,
+    base_url: String,
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaChatRequest {
+    model: String,
+    messages: Vec<OllamaMessage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<ChatCompletionToolDefinition>,
+    stream: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaMessage {
+    role: String,
+    #[serde(default)]
+    content: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<OllamaToolCall>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaToolCall {
+    function: OllamaFunctionCall,
+}
+
+/// Unlike OpenAI, Ollama sends arguments as a JSON object, not a string.
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaFunctionCall {
+    name: String,
+    arguments: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaChatResponse {
+    message: OllamaMessage,
+    prompt_eval_count: Option<u64>,
+    eval_count: Option<u64>,
+}
+
+impl OllamaProvider {
+    pub fn new(base_url: String) -> Self {
+        Self {
+            client: Client::new(),
+            base_url,
+        }
+    }
+
+    fn to_ollama_message(message: ChatCompletionMessage) -> OllamaMessage {
+        let role = match message.role {
+            ChatCompletionMessageRole::System => "system",
+            ChatCompletionMessageRole::User => "user",
+            ChatCompletionMessageRole::Assistant => "assistant",
+            ChatCompletionMessageRole::Tool => "tool",
+        };
+
+        OllamaMessage {
+            role: role.to_string(),
+            content: message.content.unwrap_or_default(),
+            tool_calls: message
+                .tool_calls
+                .unwrap_or_default()
+                .into_iter()
+                .map(|tool_call| OllamaToolCall {
+                    function: OllamaFunctionCall {
+                        name: tool_call.function.name,
+                        arguments: serde_json::from_str(&tool_call.function.arguments)
+                            .unwrap_or_else(|_| Value::Object(Default::default())),
+                    },
+                })
+                .collect(),
+        }
+    }
+
+    fn to_chat_completion_response(response: OllamaChatResponse) -> ChatCompletionResponse {
+        // Ollama does not assign ids to tool calls, so number them per response
+        let tool_calls: Vec<ChatCompletionToolCall> = response
+            .message
+            .tool_calls
+            .into_iter()
+            .enumerate()
+            .map(|(index, tool_call)| ChatCompletionToolCall {
+                id: format!("call_{}", index),
+                kind: String::from("function"),
+                function: ChatCompletionFunctionCall {
+                    name: tool_call.function.name,
+                    arguments: tool_call.function.arguments.to_string(),
+                },
+            })
+            .collect();
+
+        let content = response.message.content;
+
+        ChatCompletionResponse {
+            choices: vec![ChatCompletionChoice {
+                message: ChatCompletionMessage {
+                    role: ChatCompletionMessageRole::Assistant,
+                    content: if content.is_empty() {
+                        None
+                    } else {
+                        Some(content)
+                    },
+                    name: None,
+                    tool_call_id: None,
+                    tool_calls: if tool_calls.is_empty() {
+                        None
+                    } else {
+                        Some(tool_calls)
+                    },
+                },
+            }],
+            usage: Some(Usage {
+                prompt_tokens: response.prompt_eval_count.unwrap_or(0),
+                completion_tokens: response.eval_count.unwrap_or(0),
+            }),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OllamaProvider {
+    fn default_model(&self) -> &'static str {
+        OLLAMA_DEFAULT_MODEL
+    }
+
+    async fn send(
+        &self,
+        request: ChatCompletionRequest,
+        on_delta: &mut (dyn FnMut(String) + Send),
+    ) -> Result<ChatCompletionResponse, MinerveError> {
+        let url = format!("{}/api/chat", self.base_url);
+
+        let ollama_request = OllamaChatRequest {
+            model: request.model,
+            messages: request
+                .messages
+                .into_iter()
+                .map(Self::to_ollama_message)
+                .collect(),
+            tools: request.tools.unwrap_or_default(),
+            stream: false,
+        };
+
+        let response = self
+            .client
+            .post(url)
+            .header("Content-Type", "application/json")
+            .json(&ollama_request)
+            .send()
+            .await?;
+        let response: OllamaChatResponse = check_status(response).await?.json().await?;
+
+        let chat_response = Self::to_chat_completion_response(response);
+        if let Some(content) = &chat_response.choices[0].message.content {
+            on_delta(content.clone());
+        }
+
+        Ok(chat_response)
+    }
+}
@@ -0,0 +1,72 @@
+use async_trait::async_trait;
+use reqwest::Client;
+
+use super::{check_status, LlmProvider};
+use crate::chat::{ChatCompletionRequest, ChatCompletionResponse};
+use crate::error::MinerveError;
+use crate::stream::StreamAssembler;
+
+pub const OPENAI_DEFAULT_MODEL: &str = "gpt-4o";
+
+/// OpenAI `/chat/completions` API, also spoken by most compatible servers.
+pub struct OpenAiProvider {
+    client: Client,
+    api_key: String,
+    base_url: String,
+}
+
+impl OpenAiProvider {
+    pub fn new(api_key: String, base_url: String) -> Self {
+        Self {
+            client: Client::new(),
+            api_key,
+            base_url,
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiProvider {
+    fn default_model(&self) -> &'static str {
+        OPENAI_DEFAULT_MODEL
+    }
+
+    async fn send(
+        &self,
+        request: ChatCompletionRequest,
+        on_delta: &mut (dyn FnMut(String) + Send),
+    ) -> Result<ChatCompletionResponse, MinerveError> {
+        let url = format!("{}/chat/completions", self.base_url);
+
+        let response = self
+            .client
+            .post(url)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/json")
+            .json(&request)
+            .send()
+            .await?;
+        let mut response = check_status(response).await?;
+
+        let is_event_stream = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.starts_with("text/event-stream"))
+            .unwrap_or(false);
+
+        if !is_event_stream {
+            // Some OpenAI-compatible servers ignore `stream` and answer in one piece
+            return Ok(response.json().await?);
+        }
+
+        let mut assembler = StreamAssembler::new();
+        while let Some(bytes) = response.chunk().await? {
+            for delta in assembler.push_bytes(&bytes) {
+                on_delta(delta);
+            }
+        }
+
+        Ok(assembler.finish())
+    }
+}
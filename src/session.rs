@@ -0,0 +1,167 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::chat::ChatCompletionMessage;
+use crate::error::MinerveError;
+use crate::token_counter::TokenCounter;
+use crate::SESSIONS_DIR;
+
+const TITLE_LEN: usize = 60;
+
+/// Tokens and cost of a session, over every run that resumed it.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct SessionUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost_usd: f64,
+}
+
+impl SessionUsage {
+    /// What `token_counter` counted so far in this process.
+    pub fn counted_by(token_counter: &TokenCounter) -> Self {
+        Self {
+            prompt_tokens: token_counter.current_prompt() as u64,
+            completion_tokens: token_counter.current_completion() as u64,
+            cost_usd: token_counter.session_cost(),
+        }
+    }
+
+    /// `self` plus what was counted between `start` and `end`.
+    pub fn plus_between(self, start: SessionUsage, end: SessionUsage) -> Self {
+        Self {
+            prompt_tokens: self.prompt_tokens
+                + end.prompt_tokens.saturating_sub(start.prompt_tokens),
+            completion_tokens: self.completion_tokens
+                + end
+                    .completion_tokens
+                    .saturating_sub(start.completion_tokens),
+            cost_usd: self.cost_usd + (end.cost_usd - start.cost_usd).max(0.0),
+        }
+    }
+}
+
+/// A conversation, as saved to `~/.minerve/sessions/<id>.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub id: String,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>,
+    /// Directory the session was started in, for `--continue`.
+    pub cwd: String,
+    pub model: String,
+    /// The session this one was forked from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forked_from: Option<String>,
+    pub usage: SessionUsage,
+    /// The whole history, tool calls and tool results included.
+    pub messages: Vec<ChatCompletionMessage>,
+}
+
+/// The first line of `text`, shortened to fit in a list.
+pub fn summary_line(text: &str) -> String {
+    let first_line = text.lines().next().unwrap_or_default();
+    if first_line.chars().count() > TITLE_LEN {
+        format!(
+            "{}...",
+            first_line.chars().take(TITLE_LEN).collect::<String>()
+        )
+    } else {
+        first_line.to_string()
+    }
+}
+
+pub fn current_dir() -> String {
+    std::env::current_dir()
+        .map(|dir| dir.display().to_string())
+        .unwrap_or_default()
+}
+
+impl Session {
+    pub fn new(model: String, messages: Vec<ChatCompletionMessage>) -> Self {
+        let now = Local::now();
+        Self {
+            id: format!(
+                "{}-{:03}",
+                now.format("%Y%m%d-%H%M%S"),
+                now.timestamp_subsec_millis()
+            ),
+            created_at: now,
+            updated_at: now,
+            cwd: current_dir(),
+            model,
+            forked_from: None,
+            usage: SessionUsage::default(),
+            messages,
+        }
+    }
+
+    /// The start of the first user message, to recognize the session in a list.
+    pub fn title(&self) -> String {
+        let first_prompt = self
+            .messages
+            .iter()
+            .find(|msg| msg.is_user_prompt())
+            .and_then(|msg| msg.content.as_deref())
+            .unwrap_or("(no prompt)");
+        summary_line(first_prompt)
+    }
+
+    /// Sessions are only worth saving once the user asked something.
+    pub fn has_user_message(&self) -> bool {
+        self.messages
+            .iter()
+            .any(ChatCompletionMessage::is_user_prompt)
+    }
+}
+
+/// The saved sessions, one JSON file each.
+pub struct SessionStore {
+    dir: PathBuf,
+}
+
+impl SessionStore {
+    pub fn open_default() -> Option<Self> {
+        let dir = dirs::home_dir()?.join(SESSIONS_DIR);
+        std::fs::create_dir_all(&dir).ok()?;
+        Some(Self { dir })
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    pub fn save(&self, session: &Session) -> Result<(), MinerveError> {
+        let path = self.path(&session.id);
+        let json = serde_json::to_string_pretty(session)
+            .map_err(|err| MinerveError::json("Could not serialize the session", err))?;
+        std::fs::write(&path, json)
+            .map_err(|err| MinerveError::io(format!("Could not write {}", path.display()), err))
+    }
+
+    pub fn load(&self, id: &str) -> Result<Session, MinerveError> {
+        let path = self.path(id);
+        let json = std::fs::read_to_string(&path)
+            .map_err(|err| MinerveError::io(format!("Could not read session '{}'", id), err))?;
+        serde_json::from_str(&json)
+            .map_err(|err| MinerveError::json(format!("Could not parse session '{}'", id), err))
+    }
+
+    /// Every readable session, most recently updated first.
+    pub fn list(&self) -> Vec<Session> {
+        let mut sessions: Vec<Session> = std::fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| std::fs::read_to_string(entry.path()).ok())
+            .filter_map(|json| serde_json::from_str(&json).ok())
+            .collect();
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.updated_at));
+        sessions
+    }
+
+    /// The most recently updated session started in `cwd`.
+    pub fn latest_in(&self, cwd: &str) -> Option<Session> {
+        self.list().into_iter().find(|session| session.cwd == cwd)
+    }
+}
@@ -0,0 +1,131 @@
+use crate::chat::{
+    ChatCompletionChoice, ChatCompletionChunk, ChatCompletionFunctionCall, ChatCompletionMessage,
+    ChatCompletionMessageRole, ChatCompletionResponse, ChatCompletionToolCall, Usage,
+};
+
+#[derive(Default)]
+struct PartialToolCall {
+    id: String,
+    name: String,
+    arguments: String,
+}
+
+/// Assembles a streamed (SSE) chat completion into a regular response.
+pub struct StreamAssembler {
+    pending: Vec<u8>,
+    content: String,
+    tool_calls: Vec<PartialToolCall>,
+    usage: Option<Usage>,
+}
+
+impl StreamAssembler {
+    pub fn new() -> Self {
+        Self {
+            pending: Vec::new(),
+            content: String::new(),
+            tool_calls: Vec::new(),
+            usage: None,
+        }
+    }
+
+    /// Feeds raw body bytes and returns the content deltas completed by them.
+    pub fn push_bytes(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(bytes);
+
+        let mut deltas = Vec::new();
+        while let Some(newline) = self.pending.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=newline).collect();
+            let line = String::from_utf8_lossy(&line);
+            if let Some(delta) = self.handle_line(line.trim()) {
+                deltas.push(delta);
+            }
+        }
+        deltas
+    }
+
+    fn handle_line(&mut self, line: &str) -> Option<String> {
+        let data = line.strip_prefix("data:")?.trim();
+        if data == "[DONE]" {
+            return None;
+        }
+
+        let chunk: ChatCompletionChunk = serde_json::from_str(data).ok()?;
+        if chunk.usage.is_some() {
+            self.usage = chunk.usage;
+        }
+
+        let mut delta_content = String::new();
+        for choice in chunk.choices {
+            if let Some(content) = choice.delta.content {
+                delta_content.push_str(&content);
+            }
+            for tool_call in choice.delta.tool_calls.unwrap_or_default() {
+                if self.tool_calls.len() <= tool_call.index {
+                    self.tool_calls
+                        .resize_with(tool_call.index + 1, PartialToolCall::default);
+                }
+                let partial = &mut self.tool_calls[tool_call.index];
+                if let Some(id) = tool_call.id {
+                    partial.id.push_str(&id);
+                }
+                if let Some(function) = tool_call.function {
+                    if let Some(name) = function.name {
+                        partial.name.push_str(&name);
+                    }
+                    if let Some(arguments) = function.arguments {
+                        partial.arguments.push_str(&arguments);
+                    }
+                }
+            }
+        }
+
+        if delta_content.is_empty() {
+            None
+        } else {
+            self.content.push_str(&delta_content);
+            Some(delta_content)
+        }
+    }
+
+    pub fn finish(mut self) -> ChatCompletionResponse {
+        // Flush a trailing event that was not newline-terminated
+        if !self.pending.is_empty() {
+            let line = String::from_utf8_lossy(&std::mem::take(&mut self.pending)).to_string();
+            self.handle_line(line.trim());
+        }
+
+        let tool_calls: Vec<ChatCompletionToolCall> = self
+            .tool_calls
+            .into_iter()
+            .map(|partial| ChatCompletionToolCall {
+                id: partial.id,
+                kind: String::from("function"),
+                function: ChatCompletionFunctionCall {
+                    name: partial.name,
+                    arguments: partial.arguments,
+                },
+            })
+            .collect();
+
+        ChatCompletionResponse {
+            choices: vec![ChatCompletionChoice {
+                message: ChatCompletionMessage {
+                    role: ChatCompletionMessageRole::Assistant,
+                    content: if self.content.is_empty() {
+                        None
+                    } else {
+                        Some(self.content)
+                    },
+                    name: None,
+                    tool_call_id: None,
+                    tool_calls: if tool_calls.is_empty() {
+                        None
+                    } else {
+                        Some(tool_calls)
+                    },
+                },
+            }],
+            usage: self.usage,
+        }
+    }
+}
@@ -1,11 +1,17 @@
-use std::sync::{
-    atomic::{AtomicUsize, Ordering},
-    Arc, OnceLock,
-};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use crate::pricing::cost_for;
+use crate::usage_ledger::{UsageEntry, UsageLedger};
+
+/// Costs are accumulated in millionths of a dollar to keep them atomic.
+const MICROS_PER_DOLLAR: f64 = 1_000_000.0;
 
 pub struct TokenCounter {
     prompt_tokens: AtomicUsize,
     completion_tokens: AtomicUsize,
+    session_cost_micros: AtomicU64,
+    earlier_today_cost_micros: u64,
+    ledger: Option<UsageLedger>,
 }
 
 impl TokenCounter {
@@ -13,15 +19,45 @@ impl TokenCounter {
         TokenCounter {
             prompt_tokens: AtomicUsize::new(0),
             completion_tokens: AtomicUsize::new(0),
+            session_cost_micros: AtomicU64::new(0),
+            earlier_today_cost_micros: 0,
+            ledger: None,
         }
     }
 
-    pub fn increment_prompt(&self, count: usize) {
-        self.prompt_tokens.fetch_add(count, Ordering::SeqCst);
+    /// Creates a counter that appends every call to the usage ledger.
+    pub fn with_ledger(ledger: UsageLedger) -> Self {
+        let earlier_today = ledger.cost_on(chrono::Local::now().date_naive());
+        TokenCounter {
+            earlier_today_cost_micros: (earlier_today * MICROS_PER_DOLLAR) as u64,
+            ledger: Some(ledger),
+            ..Self::new()
+        }
     }
 
-    pub fn increment_completion(&self, count: usize) {
-        self.completion_tokens.fetch_add(count, Ordering::SeqCst);
+    pub fn record_usage(&self, model: &str, prompt_tokens: u64, completion_tokens: u64) {
+        self.prompt_tokens
+            .fetch_add(prompt_tokens as usize, Ordering::SeqCst);
+        self.completion_tokens
+            .fetch_add(completion_tokens as usize, Ordering::SeqCst);
+
+        let cost = cost_for(model, prompt_tokens, completion_tokens);
+        self.session_cost_micros
+            .fetch_add((cost * MICROS_PER_DOLLAR) as u64, Ordering::SeqCst);
+
+        if let Some(ledger) = &self.ledger {
+            let project = std::env::current_dir()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_default();
+            ledger.append(&UsageEntry {
+                timestamp: chrono::Local::now(),
+                model: model.to_string(),
+                project,
+                prompt_tokens,
+                completion_tokens,
+                cost_usd: cost,
+            });
+        }
     }
 
     pub fn current_prompt(&self) -> usize {
@@ -31,12 +67,13 @@ impl TokenCounter {
     pub fn current_completion(&self) -> usize {
         self.completion_tokens.load(Ordering::SeqCst)
     }
-}
 
-static GLOBAL_TOKEN_COUNTER: OnceLock<Arc<TokenCounter>> = OnceLock::new();
+    pub fn session_cost(&self) -> f64 {
+        self.session_cost_micros.load(Ordering::SeqCst) as f64 / MICROS_PER_DOLLAR
+    }
 
-pub fn get_global_token_counter() -> Arc<TokenCounter> {
-    GLOBAL_TOKEN_COUNTER
-        .get_or_init(|| Arc::new(TokenCounter::new()))
-        .clone()
+    /// Today's cost across every session recorded in the ledger, this one included.
+    pub fn today_cost(&self) -> f64 {
+        self.earlier_today_cost_micros as f64 / MICROS_PER_DOLLAR + self.session_cost()
+    }
 }
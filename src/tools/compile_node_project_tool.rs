@@ -23,7 +23,6 @@ impl Tool for CompileNodeProjectTool {
     async fn run(
         &self,
         _args: HashMap<String, String>,
-        _settings: ExecuteCommandSettings,
     ) -> String {
         match Command::new("sh")
             .arg("-c")
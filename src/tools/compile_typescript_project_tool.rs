@@ -1,8 +1,8 @@
-use crate::tools::{Tool, ExecuteCommandSettings};
-use std::collections::HashMap;
-use std::process::Command;
+use crate::error::MinerveError;
+use crate::tools::utils::run_process;
+use crate::tools::{Param, Tool, ToolOutput, ToolParams};
 use async_trait::async_trait;
-use serde_json::Value;
+use std::path::Path;
 
 pub struct CompileTypescriptProjectTool;
 
@@ -16,22 +16,25 @@ impl Tool for CompileTypescriptProjectTool {
         "Runs `NODE_OPTIONS=\"--max-old-space-size=8192\" yarn run tsc --incremental`"
     }
 
-    fn parameters(&self) -> HashMap<&'static str, &'static str> {
-        HashMap::new()
+    fn parameters(&self) -> Vec<Param> {
+        Vec::new()
     }
 
-    async fn run(
-        &self,
-        _args: HashMap<String, String>,
-        _settings: ExecuteCommandSettings,
-    ) -> String {
-        match Command::new("sh")
-            .arg("-c")
-            .arg("NODE_OPTIONS=\"--max-old-space-size=8192\" yarn run tsc --incremental")
-            .output()
-        {
-            Ok(output) => String::from_utf8_lossy(&output.stdout).to_string(),
-            Err(err) => err.to_string(),
-        }
+    fn applies_to_project(&self) -> bool {
+        Path::new("tsconfig.json").exists()
     }
-}
\ No newline at end of file
+
+    async fn run(&self, _params: ToolParams) -> Result<ToolOutput, MinerveError> {
+        let out = run_process(
+            "sh",
+            &[
+                "-c",
+                "NODE_OPTIONS=\"--max-old-space-size=8192\" yarn run tsc --incremental",
+            ],
+        )
+        .await
+        .map_err(|e| MinerveError::io("Failed to run tsc", e))?;
+
+        Ok(ToolOutput::from_process(&out))
+    }
+}
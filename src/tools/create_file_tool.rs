@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use std::collections::HashMap;
 use std::fs;
-use crate::tools::{ExecuteCommandSettings, Tool};
+use crate::tools::{Tool};
 
 pub struct CreateFileTool;
 
@@ -25,7 +25,6 @@ impl Tool for CreateFileTool {
     async fn run(
         &self,
         args: HashMap<String, String>,
-        _settings: ExecuteCommandSettings,
     ) -> String {
         let filepath = match args.get("filepath") {
             Some(f) => f,
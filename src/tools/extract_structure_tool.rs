@@ -1,6 +1,6 @@
-use crate::tools::{ExecuteCommandSettings, Tool};
+use crate::error::MinerveError;
+use crate::tools::{Param, ParamName, ParamType, Tool, ToolOutput, ToolParams};
 use async_trait::async_trait;
-use std::collections::HashMap;
 use std::fs::File;
 use std::io::{self, BufRead};
 use std::path::Path;
@@ -17,26 +17,24 @@ impl Tool for ExtractStructureTool {
         "Extracts structure of a file, showing nested blocks. Use this to get an overview of a code file."
     }
 
-    fn parameters(&self) -> HashMap<&'static str, &'static str> {
-        let mut params = HashMap::new();
-        params.insert("filepath", "string");
-        params
+    fn parameters(&self) -> Vec<Param> {
+        vec![Param::new(
+            ParamName::FilePath.as_str(),
+            ParamType::String,
+            "Path of the file, relative to the current directory.",
+        )]
     }
 
-    async fn run(
-        &self,
-        args: HashMap<String, String>,
-        _settings: ExecuteCommandSettings,
-    ) -> String {
-        let filepath = match args.get("filepath") {
-            Some(f) => f,
-            None => return String::from("[Error] Missing 'filepath' parameter."),
-        };
+    fn is_read_only(&self) -> bool {
+        true
+    }
 
-        match extract_structure(filepath) {
-            Ok(output) => output,
-            Err(e) => format!("[Error] Failed to extract structure: {}", e),
-        }
+    async fn run(&self, params: ToolParams) -> Result<ToolOutput, MinerveError> {
+        let filepath = params.get_string(ParamName::FilePath.as_str())?;
+
+        extract_structure(filepath)
+            .map(ToolOutput::success)
+            .map_err(|e| MinerveError::io("Failed to extract structure", e))
     }
 }
 
@@ -72,9 +70,7 @@ fn extract_structure<P: AsRef<Path>>(path: P) -> io::Result<String> {
             // optionally emit a placeholder for content
             output.push_str(&format!("{}// [...]\n", "    ".repeat(depth)));
         } else if trimmed.contains('}') {
-            if depth > 0 {
-                depth -= 1;
-            }
+            depth = depth.saturating_sub(1);
         }
     }
 
@@ -1,10 +1,10 @@
-use crate::tools::Tool;
+use crate::error::MinerveError;
+use crate::tools::{Param, Tool, ToolOutput, ToolParams};
 use async_trait::async_trait;
-use std::collections::HashMap;
 use std::fs;
 use std::process::Command;
 
-use super::{utils::truncate, ExecuteCommandSettings};
+use super::utils::truncate;
 
 pub struct GetGeneralContext;
 
@@ -18,15 +18,15 @@ impl Tool for GetGeneralContext {
         "Gets a snapshot of the current directory, git branch, staged files, and tree (excluding gitignored files)."
     }
 
-    fn parameters(&self) -> HashMap<&'static str, &'static str> {
-        HashMap::new()
+    fn parameters(&self) -> Vec<Param> {
+        Vec::new()
     }
 
-    async fn run(
-        &self,
-        _args: HashMap<String, String>,
-        _settings: ExecuteCommandSettings,
-    ) -> String {
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    async fn run(&self, _params: ToolParams) -> Result<ToolOutput, MinerveError> {
         let exec = |cmd: &str| {
             Command::new("sh")
                 .arg("-c")
@@ -52,6 +52,8 @@ impl Tool for GetGeneralContext {
             "workingTree": truncate(exec("git ls-files"), 2000)
         });
 
-        serde_json::to_string_pretty(&result).unwrap()
+        Ok(ToolOutput::success(
+            serde_json::to_string_pretty(&result).unwrap(),
+        ))
     }
 }
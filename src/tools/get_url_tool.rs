@@ -1,8 +1,8 @@
+use crate::error::MinerveError;
 use async_trait::async_trait;
 use reqwest;
-use std::collections::HashMap;
 
-use super::{ExecuteCommandSettings, Tool, ToolParams};
+use super::{Param, ParamType, Tool, ToolOutput, ToolParams};
 
 pub struct GetUrlTool;
 
@@ -16,28 +16,18 @@ impl Tool for GetUrlTool {
         "Fetches the content of a URL as plaintext."
     }
 
-    fn parameters(&self) -> HashMap<&'static str, &'static str> {
-        let mut params = HashMap::new();
-        params.insert("url", "string");
-        params
+    fn parameters(&self) -> Vec<Param> {
+        vec![Param::new("url", ParamType::String, "The URL to fetch.")]
     }
 
-    async fn run(
-        &self,
-        args: HashMap<String, String>,
-        _settings: ExecuteCommandSettings,
-    ) -> String {
-        let url = match args.get("url") {
-            Some(u) => u,
-            None => return "[Error] URL parameter is missing.".to_string(),
-        };
-
-        match reqwest::get(url).await {
-            Ok(response) => match response.text().await {
-                Ok(text) => text,
-                Err(_) => "[Error] Failed to convert response to text.".to_string(),
-            },
-            Err(_) => "[Error] Failed to fetch the URL.".to_string(),
-        }
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    async fn run(&self, params: ToolParams) -> Result<ToolOutput, MinerveError> {
+        let url = params.get_string("url")?;
+
+        let response = reqwest::get(&url).await?;
+        Ok(ToolOutput::success(response.text().await?))
     }
 }
@@ -1,6 +1,6 @@
-use crate::tools::{ExecuteCommandSettings, Tool};
+use crate::error::MinerveError;
+use crate::tools::{Param, ParamType, Tool, ToolOutput, ToolParams};
 use async_trait::async_trait;
-use std::collections::HashMap;
 use std::process::Command;
 
 pub struct GitDiffTool;
@@ -15,21 +15,29 @@ impl Tool for GitDiffTool {
         "Gets the current git diff of the repository."
     }
 
-    fn parameters(&self) -> HashMap<&'static str, &'static str> {
-        HashMap::new()
+    fn parameters(&self) -> Vec<Param> {
+        vec![Param::new(
+            "paths",
+            ParamType::Array(Box::new(ParamType::String)),
+            "Only show the changes to these files or directories.",
+        )
+        .optional()]
     }
 
-    async fn run(
-        &self,
-        _args: HashMap<String, String>,
-        _settings: ExecuteCommandSettings,
-    ) -> String {
-        let output = Command::new("git")
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    async fn run(&self, params: ToolParams) -> Result<ToolOutput, MinerveError> {
+        let paths = params.get_string_array("paths")?;
+
+        let out = Command::new("git")
             .arg("diff")
+            .arg("--")
+            .args(&paths)
             .output()
-            .map(|out| String::from_utf8_lossy(&out.stdout).to_string())
-            .unwrap_or_else(|e| format!("[Error] {}", e));
+            .map_err(|e| MinerveError::io("Failed to run git diff", e))?;
 
-        output
+        Ok(ToolOutput::from_process(&out))
     }
 }
@@ -1,6 +1,6 @@
-use crate::tools::{ExecuteCommandSettings, Tool};
+use crate::error::MinerveError;
+use crate::tools::{Param, Tool, ToolOutput, ToolParams};
 use async_trait::async_trait;
-use std::collections::HashMap;
 use std::process::Command;
 
 pub struct GitStatusTool;
@@ -15,21 +15,20 @@ impl Tool for GitStatusTool {
         "Gets the current git status of the repository."
     }
 
-    fn parameters(&self) -> HashMap<&'static str, &'static str> {
-        HashMap::new()
+    fn parameters(&self) -> Vec<Param> {
+        Vec::new()
     }
 
-    async fn run(
-        &self,
-        _args: HashMap<String, String>,
-        _settings: ExecuteCommandSettings,
-    ) -> String {
-        let output = Command::new("git")
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    async fn run(&self, _params: ToolParams) -> Result<ToolOutput, MinerveError> {
+        let out = Command::new("git")
             .arg("status")
             .output()
-            .map(|out| String::from_utf8_lossy(&out.stdout).to_string())
-            .unwrap_or_else(|e| format!("[Error] {}", e));
+            .map_err(|e| MinerveError::io("Failed to run git status", e))?;
 
-        output
+        Ok(ToolOutput::from_process(&out))
     }
 }
@@ -1,10 +1,8 @@
-use crate::tools::{ParamName, Tool, ToolParams};
+use crate::error::MinerveError;
+use crate::tools::{Param, ParamName, ParamType, Tool, ToolOutput, ToolParams};
 use async_trait::async_trait;
-use std::collections::HashMap;
 use std::fs;
 
-use super::ExecuteCommandSettings;
-
 pub struct ListFilesTool;
 
 #[async_trait]
@@ -17,25 +15,29 @@ impl Tool for ListFilesTool {
         "Lists files in a directory"
     }
 
-    fn parameters(&self) -> HashMap<&'static str, &'static str> {
-        let mut params = HashMap::new();
-        params.insert(ParamName::Dir.as_str(), "string");
-        params
+    fn parameters(&self) -> Vec<Param> {
+        vec![Param::new(
+            ParamName::Dir.as_str(),
+            ParamType::String,
+            "Directory to list, relative to the current directory.",
+        )
+        .with_default(".")]
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
     }
 
-    async fn run(
-        &self,
-        args: HashMap<String, String>,
-        _settings: ExecuteCommandSettings,
-    ) -> String {
-        let params = ToolParams::new(args);
-        let dir = params.get_string_optional(ParamName::Dir.as_str(), ".");
-        match fs::read_dir(&dir) {
-            Ok(entries) => entries
+    async fn run(&self, params: ToolParams) -> Result<ToolOutput, MinerveError> {
+        let dir = params.get_string(ParamName::Dir.as_str())?;
+        let entries =
+            fs::read_dir(&dir).map_err(|e| MinerveError::io("Failed to list files", e))?;
+
+        Ok(ToolOutput::success(
+            entries
                 .filter_map(|e| e.ok().map(|f| f.file_name().to_string_lossy().into_owned()))
                 .collect::<Vec<_>>()
                 .join("\n"),
-            Err(e) => format!("[Error] Failed to list files: {}", e),
-        }
+        ))
     }
 }
@@ -1,7 +1,11 @@
 use async_trait::async_trait;
-use serde_json::Value;
-use std::collections::HashMap;
+use serde_json::{json, Value};
+use std::process::Output;
 
+use crate::error::MinerveError;
+use utils::truncate;
+
+pub mod compile_typescript_project_tool;
 pub mod extract_structure_tool;
 pub mod get_general_context_tool;
 pub mod get_url_tool;
@@ -9,7 +13,6 @@ pub mod git_diff_cached_tool;
 pub mod git_diff_tool;
 pub mod git_status_tool;
 pub mod list_files_tool;
-pub mod compile_typescript_project_tool;
 pub mod registry;
 pub mod replace_content_tool;
 pub mod run_cargo_check_tool;
@@ -41,78 +44,371 @@ impl ParamName {
     }
 }
 
+/// JSON type of a tool parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamType {
+    String,
+    Integer,
+    Boolean,
+    /// A list whose items all have the given type.
+    Array(Box<ParamType>),
+    /// A string taking one of the given values.
+    Enum(&'static [&'static str]),
+}
+
+impl ParamType {
+    fn schema(&self) -> Value {
+        match self {
+            ParamType::String => json!({ "type": "string" }),
+            ParamType::Integer => json!({ "type": "integer" }),
+            ParamType::Boolean => json!({ "type": "boolean" }),
+            ParamType::Array(items) => json!({ "type": "array", "items": items.schema() }),
+            ParamType::Enum(values) => json!({ "type": "string", "enum": values }),
+        }
+    }
+
+    /// Whether `value` has this type.
+    fn accepts(&self, value: &Value) -> bool {
+        match (self, value) {
+            (ParamType::String, Value::String(_)) => true,
+            (ParamType::Integer, value) => value.is_i64() || value.is_u64(),
+            (ParamType::Boolean, Value::Bool(_)) => true,
+            (ParamType::Array(items), Value::Array(values)) => {
+                values.iter().all(|value| items.accepts(value))
+            }
+            (ParamType::Enum(allowed), Value::String(value)) => allowed.contains(&value.as_str()),
+            _ => false,
+        }
+    }
+
+    /// Models sometimes quote numbers and booleans; reads them back.
+    fn coerce(&self, value: Value) -> Value {
+        match (self, &value) {
+            (ParamType::Integer, Value::String(s)) => {
+                s.trim().parse::<i64>().map(Value::from).unwrap_or(value)
+            }
+            (ParamType::Boolean, Value::String(s)) => match s.trim() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                _ => value,
+            },
+            _ => value,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            ParamType::String => String::from("a string"),
+            ParamType::Integer => String::from("an integer"),
+            ParamType::Boolean => String::from("a boolean"),
+            ParamType::Array(items) => format!("a list of {}s", items.name()),
+            ParamType::Enum(values) => format!("one of {}", values.join(", ")),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            ParamType::String | ParamType::Enum(_) => "string",
+            ParamType::Integer => "integer",
+            ParamType::Boolean => "boolean",
+            ParamType::Array(_) => "array",
+        }
+    }
+}
+
+/// A parameter of a tool, as advertised to the model.
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: &'static str,
+    pub param_type: ParamType,
+    pub description: &'static str,
+    pub required: bool,
+    /// Used when the model leaves the parameter out.
+    pub default: Option<Value>,
+}
+
+impl Param {
+    /// A required parameter.
+    pub fn new(name: &'static str, param_type: ParamType, description: &'static str) -> Self {
+        Self {
+            name,
+            param_type,
+            description,
+            required: true,
+            default: None,
+        }
+    }
+
+    pub fn optional(mut self) -> Self {
+        self.required = false;
+        self
+    }
+
+    /// Makes the parameter optional, with `default` when left out.
+    pub fn with_default(mut self, default: impl Into<Value>) -> Self {
+        self.required = false;
+        self.default = Some(default.into());
+        self
+    }
+
+    fn schema(&self) -> Value {
+        let mut schema = self.param_type.schema();
+        schema["description"] = json!(self.description);
+        if let Some(default) = &self.default {
+            schema["default"] = default.clone();
+        }
+        schema
+    }
+}
+
+/// JSON Schema of an object with `params` as properties.
+pub fn parameters_schema(params: &[Param]) -> Value {
+    let properties: serde_json::Map<String, Value> = params
+        .iter()
+        .map(|param| (param.name.to_string(), param.schema()))
+        .collect();
+    let required: Vec<&str> = params
+        .iter()
+        .filter(|param| param.required)
+        .map(|param| param.name)
+        .collect();
+
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required
+    })
+}
+
+/// Arguments of a tool call, checked against the tool's parameters.
 pub struct ToolParams {
-    args: HashMap<String, String>,
+    args: serde_json::Map<String, Value>,
 }
 
 impl ToolParams {
-    pub fn new(args: HashMap<String, String>) -> Self {
-        Self { args }
+    /// Checks `args` against `params` and fills in the defaults of those left
+    /// out. Arguments the tool does not declare are kept as they are.
+    pub fn parse(
+        mut args: serde_json::Map<String, Value>,
+        params: &[Param],
+    ) -> Result<Self, MinerveError> {
+        for param in params {
+            match args.remove(param.name).filter(|value| !value.is_null()) {
+                Some(value) => {
+                    let value = param.param_type.coerce(value);
+                    if !param.param_type.accepts(&value) {
+                        return Err(MinerveError::ToolArgument(format!(
+                            "Parameter '{}' must be {}, got: {}",
+                            param.name,
+                            param.param_type.describe(),
+                            value
+                        )));
+                    }
+                    args.insert(param.name.to_string(), value);
+                }
+                None if param.required => {
+                    return Err(MinerveError::ToolArgument(format!(
+                        "Parameter '{}' is required.",
+                        param.name
+                    )))
+                }
+                None => {
+                    if let Some(default) = &param.default {
+                        args.insert(param.name.to_string(), default.clone());
+                    }
+                }
+            }
+        }
+        Ok(Self { args })
     }
 
-    pub fn get_string(&self, param: &str) -> Result<String, String> {
-        self.args
-            .get(param)
-            .cloned()
+    /// The arguments as a JSON object, as given to hooks.
+    pub fn to_json(&self) -> Value {
+        Value::Object(self.args.clone())
+    }
+
+    fn wrong_type(param: &str, expected: &str) -> MinerveError {
+        MinerveError::ToolArgument(format!("Parameter '{}' must be {}.", param, expected))
+    }
+
+    /// A required, non-empty string.
+    pub fn get_string(&self, param: &str) -> Result<String, MinerveError> {
+        self.get_optional_string(param)?
             .filter(|s| !s.is_empty())
             .ok_or_else(|| {
-                format!(
-                    "[Error] Parameter '{}' is required and must be a non-empty string.",
+                MinerveError::ToolArgument(format!(
+                    "Parameter '{}' is required and must be a non-empty string.",
                     param
-                )
+                ))
             })
     }
 
-    pub fn get_string_optional(&self, param: &str, default: &str) -> String {
+    /// A string that may be left out or empty.
+    pub fn get_optional_string(&self, param: &str) -> Result<Option<String>, MinerveError> {
+        match self.args.get(param) {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::String(s)) => Ok(Some(s.clone())),
+            Some(_) => Err(Self::wrong_type(param, "a string")),
+        }
+    }
+
+    pub fn get_integer(&self, param: &str) -> Result<i64, MinerveError> {
+        self.get_optional_integer(param)?
+            .ok_or_else(|| Self::wrong_type(param, "an integer"))
+    }
+
+    pub fn get_optional_integer(&self, param: &str) -> Result<Option<i64>, MinerveError> {
+        match self.args.get(param) {
+            None | Some(Value::Null) => Ok(None),
+            Some(value) => value
+                .as_i64()
+                .map(Some)
+                .ok_or_else(|| Self::wrong_type(param, "an integer")),
+        }
+    }
+
+    pub fn get_bool(&self, param: &str) -> Result<bool, MinerveError> {
         self.args
             .get(param)
-            .cloned()
-            .unwrap_or_else(|| default.to_string())
+            .and_then(Value::as_bool)
+            .ok_or_else(|| Self::wrong_type(param, "a boolean"))
+    }
+
+    /// A list of strings; empty when left out.
+    pub fn get_string_array(&self, param: &str) -> Result<Vec<String>, MinerveError> {
+        match self.args.get(param) {
+            None | Some(Value::Null) => Ok(Vec::new()),
+            Some(Value::Array(values)) => values
+                .iter()
+                .map(|value| value.as_str().map(str::to_string))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| Self::wrong_type(param, "a list of strings")),
+            Some(_) => Err(Self::wrong_type(param, "a list of strings")),
+        }
     }
 }
 
-pub struct ExecuteCommandSettings {
-    pub is_headless: bool,
+/// Whether a tool did what was asked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolStatus {
+    Success,
+    Error,
 }
 
-impl Default for ExecuteCommandSettings {
-    fn default() -> Self {
-        Self { is_headless: false }
+impl ToolStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ToolStatus::Success => "success",
+            ToolStatus::Error => "error",
+        }
     }
 }
 
-#[async_trait]
-pub trait Tool: Send + Sync {
-    fn name(&self) -> &'static str;
-    fn description(&self) -> &'static str;
-    fn parameters(&self) -> HashMap<&'static str, &'static str>;
+/// What a tool call produced. A tool that could not run at all, e.g. for
+/// bad arguments, returns a `MinerveError` instead.
+#[derive(Debug, Clone)]
+pub struct ToolOutput {
+    pub status: ToolStatus,
+    pub content: String,
+    /// `content` was shortened by `utils::truncate`.
+    pub truncated: bool,
+    /// Files the tool created or changed.
+    pub files_touched: Vec<String>,
+    /// Exit code of the process the tool ran.
+    pub exit_code: Option<i32>,
+}
 
-    async fn run(&self, args: HashMap<String, String>, _settings: ExecuteCommandSettings)
-        -> String;
+impl ToolOutput {
+    pub fn success(content: impl Into<String>) -> Self {
+        Self {
+            status: ToolStatus::Success,
+            content: content.into(),
+            truncated: false,
+            files_touched: Vec::new(),
+            exit_code: None,
+        }
+    }
 
-    fn function_definition(&self) -> Value {
-        let mut properties = serde_json::Map::new();
-        let mut required = Vec::new();
-
-        for (param_name, param_type) in self.parameters() {
-            if param_type == "string" {
-                properties.insert(
-                    param_name.to_string(),
-                    serde_json::json!({"type": "string"}),
-                );
-            } else if param_type == "integer" {
-                properties.insert(
-                    param_name.to_string(),
-                    serde_json::json!({"type": "integer"}),
-                );
+    pub fn error(content: impl Into<String>) -> Self {
+        Self {
+            status: ToolStatus::Error,
+            ..Self::success(content)
+        }
+    }
+
+    /// The stdout of a process followed by its stderr, failed when the
+    /// process exited with a non-zero code.
+    pub fn from_process(out: &Output) -> Self {
+        let stdout = String::from_utf8_lossy(&out.stdout);
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        let content = if stderr.is_empty() {
+            stdout.to_string()
+        } else {
+            format!("{}\n{}", stdout, stderr)
+        };
+
+        let output = if out.status.success() {
+            Self::success(content)
+        } else {
+            Self::error(content)
+        };
+        Self {
+            exit_code: out.status.code(),
+            ..output
+        }
+    }
+
+    /// Cuts the content to `limit` bytes with `utils::truncate`.
+    pub fn truncated_to(self, limit: usize) -> Self {
+        Self {
+            truncated: self.truncated || self.content.len() > limit,
+            content: truncate(self.content, limit),
+            ..self
+        }
+    }
+
+    pub fn touching(mut self, path: impl Into<String>) -> Self {
+        self.files_touched.push(path.into());
+        self
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.status == ToolStatus::Success
+    }
+
+    /// The tool message sent back to the model.
+    pub fn to_message(&self) -> String {
+        match (self.status, self.exit_code) {
+            (ToolStatus::Success, _) => self.content.clone(),
+            (ToolStatus::Error, Some(code)) => {
+                format!("[Error] Exited with code {}.\n{}", code, self.content)
             }
-            required.push(param_name.to_string());
+            (ToolStatus::Error, None) => format!("[Error] {}", self.content),
         }
+    }
+}
+
+#[async_trait]
+pub trait Tool: Send + Sync {
+    fn name(&self) -> &str;
+    fn description(&self) -> &str;
+    fn parameters(&self) -> Vec<Param>;
 
-        serde_json::json!({
-            "type": "object",
-            "properties": properties,
-            "required": required
-        })
+    /// Read-only tools are the only ones offered while planning.
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    /// Whether the tool is of use in the current directory, e.g. a build
+    /// command for the project's language. Others are only offered when
+    /// enabled in the config.
+    fn applies_to_project(&self) -> bool {
+        true
+    }
+
+    async fn run(&self, params: ToolParams) -> Result<ToolOutput, MinerveError>;
+
+    fn function_definition(&self) -> Value {
+        parameters_schema(&self.parameters())
     }
 }
@@ -1,7 +1,12 @@
+use crate::config::{McpServerConfig, ToolSelection, ToolsConfig};
+use crate::engine::AgentMode;
+use crate::error::MinerveError;
+use crate::mcp::client::McpClient;
 use crate::tools::Tool;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
+use super::compile_typescript_project_tool::CompileTypescriptProjectTool;
 use super::extract_structure_tool::ExtractStructureTool;
 use super::get_general_context_tool::GetGeneralContext;
 use super::get_url_tool::GetUrlTool;
@@ -15,32 +20,104 @@ use super::run_shell_command_tool::RunShellCommandTool;
 use super::search_for_path_pattern_tool::SearchForPathPatternTool;
 use super::search_for_string_tool::SearchForStringTool;
 use super::set_whole_file_contents_tool::SetWholeFileContentsTool;
-use super::compile_typescript_project_tool::CompileTypescriptProjectTool;
 use super::show_file_tool::ShowFileTool;
 
-pub fn get_tool_registry() -> HashMap<&'static str, Arc<dyn Tool>> {
-    let mut map: HashMap<&'static str, Arc<dyn Tool>> = HashMap::new();
-
-    map.insert("get_general_context", Arc::new(GetGeneralContext));
-    map.insert("search_for_string", Arc::new(SearchForStringTool));
-    map.insert(
-        "search_for_path_pattern",
+fn builtin_tools() -> Vec<Arc<dyn Tool>> {
+    vec![
+        Arc::new(GetGeneralContext),
+        Arc::new(SearchForStringTool),
         Arc::new(SearchForPathPatternTool),
-    );
-    map.insert("list_files", Arc::new(ListFilesTool));
-    map.insert("git_status", Arc::new(GitStatusTool));
-    map.insert("git_diff", Arc::new(GitDiffTool));
-    map.insert("git_diff_cached", Arc::new(GitDiffCachedTool));
-    map.insert("show_file", Arc::new(ShowFileTool));
-    map.insert("replace_content", Arc::new(ReplaceContentTool));
-    map.insert("run_cargo_check", Arc::new(RunCargoCheckTool));
-    map.insert("run_shell_command", Arc::new(RunShellCommandTool));
-    map.insert(
-        "set_whole_file_contents",
+        Arc::new(ListFilesTool),
+        Arc::new(GitStatusTool),
+        Arc::new(GitDiffTool),
+        Arc::new(GitDiffCachedTool),
+        Arc::new(ShowFileTool),
+        Arc::new(ReplaceContentTool),
+        Arc::new(RunCargoCheckTool),
+        Arc::new(RunShellCommandTool),
         Arc::new(SetWholeFileContentsTool),
-    );
-    map.insert("extract_structure", Arc::new(ExtractStructureTool));
-    map.insert("compile_typescript_project", Arc::new(CompileTypescriptProjectTool));
-    map.insert("get_url", Arc::new(GetUrlTool));
-    map
+        Arc::new(ExtractStructureTool),
+        Arc::new(CompileTypescriptProjectTool),
+        Arc::new(GetUrlTool),
+    ]
+}
+
+/// The tools of a session, built once from the config.
+pub struct ToolRegistry {
+    tools: BTreeMap<String, Arc<dyn Tool>>,
+    selection: ToolSelection,
+    modes: HashMap<AgentMode, ToolSelection>,
+}
+
+impl ToolRegistry {
+    /// Keeps the built-in tools that apply to the current directory, then
+    /// those the config enables, minus those it disables.
+    pub fn from_config(config: &ToolsConfig) -> Self {
+        let mut registry = Self {
+            tools: BTreeMap::new(),
+            selection: config.selection.clone(),
+            modes: config.modes.clone(),
+        };
+        for tool in builtin_tools() {
+            registry.add(tool);
+        }
+        registry
+    }
+
+    /// Adds `tool` unless the config disables it.
+    pub fn add(&mut self, tool: Arc<dyn Tool>) {
+        if self.selection.keeps(tool.name(), tool.applies_to_project()) {
+            self.tools.insert(tool.name().to_string(), tool);
+        }
+    }
+
+    /// Starts the MCP servers and adds their tools. A server that fails to
+    /// start is left out, and its error returned.
+    pub async fn connect_mcp_servers(
+        &mut self,
+        servers: &BTreeMap<String, McpServerConfig>,
+    ) -> Vec<MinerveError> {
+        let mut errors = Vec::new();
+        for (name, config) in servers {
+            let tools = match McpClient::connect(name, config).await {
+                Ok(client) => client.list_tools().await,
+                Err(err) => Err(err),
+            };
+            match tools {
+                Ok(tools) => {
+                    for tool in tools {
+                        self.add(Arc::new(tool));
+                    }
+                }
+                Err(err) => errors.push(err),
+            }
+        }
+        errors
+    }
+
+    /// The tool named `name`, if enabled in any mode.
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn Tool>> {
+        self.tools.get(name)
+    }
+
+    /// Whether `tool` may be used in `mode`. The mode's own selection in the
+    /// config overrides its default, except that read-only modes can only
+    /// lose tools.
+    pub fn allows(&self, mode: AgentMode, tool: &dyn Tool) -> bool {
+        let default = mode.allows(tool);
+        match (self.modes.get(&mode), mode) {
+            (Some(selection), AgentMode::Planning | AgentMode::Delegated) => {
+                default && selection.keeps(tool.name(), default)
+            }
+            (Some(selection), _) => selection.keeps(tool.name(), default),
+            (None, _) => default,
+        }
+    }
+
+    /// The tools available in `mode`, by name.
+    pub fn tools(&self, mode: AgentMode) -> impl Iterator<Item = &Arc<dyn Tool>> {
+        self.tools
+            .values()
+            .filter(move |tool| self.allows(mode, tool.as_ref()))
+    }
 }
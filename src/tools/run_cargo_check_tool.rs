@@ -1,7 +1,8 @@
-use crate::tools::{ExecuteCommandSettings, Tool};
+use crate::error::MinerveError;
+use crate::tools::utils::run_process;
+use crate::tools::{Param, ParamType, Tool, ToolOutput, ToolParams};
 use async_trait::async_trait;
-use std::collections::HashMap;
-use std::process::Command;
+use std::path::Path;
 
 pub struct RunCargoCheckTool;
 
@@ -12,37 +13,28 @@ impl Tool for RunCargoCheckTool {
     }
 
     fn description(&self) -> &'static str {
-        "Runs `cargo check` in the current directory."
+        "Runs `cargo check`, or `cargo clippy`, in the current directory."
     }
 
-    fn parameters(&self) -> HashMap<&'static str, &'static str> {
-        HashMap::new()
+    fn parameters(&self) -> Vec<Param> {
+        vec![Param::new(
+            "subcommand",
+            ParamType::Enum(&["check", "clippy"]),
+            "Which cargo command to run.",
+        )
+        .with_default("check")]
     }
 
-    async fn run(
-        &self,
-        _args: HashMap<String, String>,
-        _settings: ExecuteCommandSettings,
-    ) -> String {
-        let output = Command::new("cargo")
-            .arg("check")
-            .output()
-            .map(|out| {
-                if out.status.success() {
-                    let stdout = String::from_utf8_lossy(&out.stdout);
-                    let stderr = String::from_utf8_lossy(&out.stderr);
-                    let out = if !stderr.is_empty() {
-                        format!("{}\n{}", stdout, stderr)
-                    } else {
-                        stdout.to_string()
-                    };
-                    out.to_string()
-                } else {
-                    format!("[Error] {}", String::from_utf8_lossy(&out.stderr))
-                }
-            })
-            .unwrap_or_else(|e| format!("[Error] {}", e));
+    fn applies_to_project(&self) -> bool {
+        Path::new("Cargo.toml").exists()
+    }
+
+    async fn run(&self, params: ToolParams) -> Result<ToolOutput, MinerveError> {
+        let subcommand = params.get_string("subcommand")?;
+        let out = run_process("cargo", &[subcommand.as_str()])
+            .await
+            .map_err(|e| MinerveError::io(format!("Failed to run cargo {}", subcommand), e))?;
 
-        output
+        Ok(ToolOutput::from_process(&out))
     }
 }
@@ -1,8 +1,8 @@
 use async_trait::async_trait;
-use std::collections::HashMap;
-use std::io::{self, Write};
 
-use super::{ExecuteCommandSettings, Tool, ToolParams};
+use super::utils::run_process;
+use super::{Param, ParamType, Tool, ToolOutput, ToolParams};
+use crate::error::MinerveError;
 
 pub struct RunShellCommandTool;
 
@@ -13,66 +13,30 @@ impl Tool for RunShellCommandTool {
     }
 
     fn description(&self) -> &'static str {
-        "Runs a shell command. Use external UI for confirmation."
+        "Runs a shell command. The user is asked to confirm before it runs."
     }
 
-    fn parameters(&self) -> HashMap<&'static str, &'static str> {
-        let mut params = HashMap::new();
-        params.insert("command", "string");
-        params.insert("is_headless", "string"); // optional param
-        params
+    fn parameters(&self) -> Vec<Param> {
+        vec![Param::new(
+            "command",
+            ParamType::String,
+            "The command line, run with `sh -c`.",
+        )]
     }
 
-    async fn run(&self, args: HashMap<String, String>, settings: ExecuteCommandSettings) -> String {
-        self.run_with_settings(args, settings).await
+    async fn run(&self, params: ToolParams) -> Result<ToolOutput, MinerveError> {
+        let command = params.get_string("command")?;
+        Self::execute_command(&command).await
     }
 }
 
 impl RunShellCommandTool {
-    pub async fn run_with_settings(
-        &self,
-        args: HashMap<String, String>,
-        settings: ExecuteCommandSettings,
-    ) -> String {
-        let params = ToolParams::new(args);
-        let command = match params.get_string("command") {
-            Ok(cmd) => cmd,
-            Err(e) => return e,
-        };
-        Self::execute_command(&command, Some(settings))
-    }
-
-    pub fn execute_command(command: &str, settings: Option<ExecuteCommandSettings>) -> String {
-        let settings = settings.unwrap_or_default();
-
-        if settings.is_headless {
-            // Prompt user for confirmation in headless mode
-            print!("Do you want to run the command '{}'? (y/n): ", command);
-            io::stdout().flush().unwrap();
-
-            let mut input = String::new();
-            if let Err(_) = io::stdin().read_line(&mut input) {
-                return String::from("[Error] Failed to read user input");
-            }
-
-            let input = input.trim().to_lowercase();
-            if input != "y" && input != "yes" {
-                return String::from("Command execution cancelled by user.");
-            }
-        }
+    /// Runs `command` through `sh`; callers are responsible for approval.
+    pub async fn execute_command(command: &str) -> Result<ToolOutput, MinerveError> {
+        let out = run_process("sh", &["-c", command])
+            .await
+            .map_err(|e| MinerveError::io("Failed to run command", e))?;
 
-        let output = std::process::Command::new("sh")
-            .arg("-c")
-            .arg(command)
-            .output()
-            .map(|out| {
-                if out.status.success() {
-                    String::from_utf8_lossy(&out.stdout).to_string()
-                } else {
-                    format!("[Error] {}", String::from_utf8_lossy(&out.stderr))
-                }
-            })
-            .unwrap_or_else(|e| format!("[Error] {}", e));
-        output
+        Ok(ToolOutput::from_process(&out))
     }
 }
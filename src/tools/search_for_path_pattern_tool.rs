@@ -1,8 +1,6 @@
-use super::utils::truncate;
-use super::ExecuteCommandSettings;
-use crate::tools::{ParamName, Tool, ToolParams};
+use crate::error::MinerveError;
+use crate::tools::{Param, ParamName, ParamType, Tool, ToolOutput, ToolParams};
 use async_trait::async_trait;
-use std::collections::HashMap;
 use std::process::Command;
 
 pub struct SearchForPathPatternTool;
@@ -17,22 +15,20 @@ impl Tool for SearchForPathPatternTool {
         "Searches for a path pattern in the current directory using ag or grep, excluding gitignored files."
     }
 
-    fn parameters(&self) -> HashMap<&'static str, &'static str> {
-        let mut params = HashMap::new();
-        params.insert(ParamName::PathPattern.as_str(), "string");
-        params
+    fn parameters(&self) -> Vec<Param> {
+        vec![Param::new(
+            ParamName::PathPattern.as_str(),
+            ParamType::String,
+            "Pattern to look for in file contents and paths.",
+        )]
     }
 
-    async fn run(
-        &self,
-        args: HashMap<String, String>,
-        _settings: ExecuteCommandSettings,
-    ) -> String {
-        let params = ToolParams::new(args);
-        let pattern = match params.get_string(ParamName::PathPattern.as_str()) {
-            Ok(s) => s,
-            Err(e) => return e,
-        };
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    async fn run(&self, params: ToolParams) -> Result<ToolOutput, MinerveError> {
+        let pattern = params.get_string(ParamName::PathPattern.as_str())?;
 
         let ag_check = Command::new("sh")
             .arg("-c")
@@ -55,8 +51,8 @@ impl Tool for SearchForPathPatternTool {
             .arg(&command)
             .output()
             .map(|out| String::from_utf8_lossy(&out.stdout).to_string())
-            .unwrap_or_else(|e| format!("[Error] {}", e));
+            .map_err(|e| MinerveError::io("Failed to run search", e))?;
 
-        truncate(output, 2000)
+        Ok(ToolOutput::success(output).truncated_to(2000))
     }
 }
@@ -1,9 +1,8 @@
-use super::utils::truncate;
-use super::ExecuteCommandSettings;
-use crate::tools::{ParamName, Tool, ToolParams};
+use crate::error::MinerveError;
+use crate::tools::utils::run_process;
+use crate::tools::{Param, ParamName, ParamType, Tool, ToolOutput, ToolParams};
 use async_trait::async_trait;
-use std::collections::HashMap;
-use std::process::Command;
+use std::io;
 
 pub struct SearchForStringTool;
 
@@ -17,49 +16,49 @@ impl Tool for SearchForStringTool {
         "Searches for a string in the current directory using ag or grep, excluding gitignored files."
     }
 
-    fn parameters(&self) -> HashMap<&'static str, &'static str> {
-        let mut params = HashMap::new();
-        params.insert(ParamName::SearchString.as_str(), "string");
-        params
+    fn parameters(&self) -> Vec<Param> {
+        vec![
+            Param::new(
+                ParamName::SearchString.as_str(),
+                ParamType::String,
+                "The string to look for.",
+            ),
+            Param::new(
+                "ignore_case",
+                ParamType::Boolean,
+                "Match regardless of case.",
+            )
+            .with_default(false),
+        ]
     }
 
-    async fn run(
-        &self,
-        args: HashMap<String, String>,
-        _settings: ExecuteCommandSettings,
-    ) -> String {
-        let params = ToolParams::new(args);
-        let search_string = match params.get_string(ParamName::SearchString.as_str()) {
-            Ok(s) => s,
-            Err(e) => return e,
-        };
+    fn is_read_only(&self) -> bool {
+        true
+    }
 
-        let ag_check = Command::new("sh")
-            .arg("-c")
-            .arg("command -v ag")
-            .output()
-            .map(|o| o.status.success())
-            .unwrap_or(false);
+    async fn run(&self, params: ToolParams) -> Result<ToolOutput, MinerveError> {
+        let search_string = params.get_string(ParamName::SearchString.as_str())?;
+        let ignore_case = params.get_bool("ignore_case")?;
 
-        let command = if ag_check {
-            format!(
-                "ag --ignore .git --ignore node_modules \"{}\"",
-                search_string
-            )
-        } else {
-            format!(
-                "grep -r --exclude-dir={{.git,node_modules}} \"{}\" .",
-                search_string
-            )
-        };
+        // The pattern is passed as its own argument, never through a shell
+        let mut ag_args = vec!["--ignore", ".git", "--ignore", "node_modules"];
+        let mut grep_args = vec!["-r", "--exclude-dir=.git", "--exclude-dir=node_modules"];
+        if ignore_case {
+            ag_args.push("-i");
+            grep_args.push("-i");
+        }
+        ag_args.extend(["--", search_string.as_str()]);
+        grep_args.extend(["--", search_string.as_str(), "."]);
 
-        let output = Command::new("sh")
-            .arg("-c")
-            .arg(&command)
-            .output()
-            .map(|out| String::from_utf8_lossy(&out.stdout).to_string())
-            .unwrap_or_else(|e| format!("[Error] {}", e));
+        let output = match run_process("ag", &ag_args).await {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                run_process("grep", &grep_args).await
+            }
+            result => result,
+        }
+        .map(|out| String::from_utf8_lossy(&out.stdout).to_string())
+        .map_err(|e| MinerveError::io("Failed to run search", e))?;
 
-        truncate(output, 2000)
+        Ok(ToolOutput::success(output).truncated_to(2000))
     }
 }
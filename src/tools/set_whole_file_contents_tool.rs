@@ -1,7 +1,6 @@
-use super::ExecuteCommandSettings;
-use crate::tools::{ParamName, Tool, ToolParams};
+use crate::error::MinerveError;
+use crate::tools::{Param, ParamName, ParamType, Tool, ToolOutput, ToolParams};
 use async_trait::async_trait;
-use std::collections::HashMap;
 
 pub struct SetWholeFileContentsTool;
 
@@ -15,31 +14,31 @@ impl Tool for SetWholeFileContentsTool {
         "Sets the entire contents of a file to the provided content. Only use this for files you fully understand."
     }
 
-    fn parameters(&self) -> HashMap<&'static str, &'static str> {
-        let mut params = HashMap::new();
-        params.insert(ParamName::FilePath.as_str(), "string");
-        params.insert(ParamName::Content.as_str(), "string");
-        params
+    fn parameters(&self) -> Vec<Param> {
+        vec![
+            Param::new(
+                ParamName::FilePath.as_str(),
+                ParamType::String,
+                "Path of the file to write, relative to the current directory.",
+            ),
+            Param::new(
+                ParamName::Content.as_str(),
+                ParamType::String,
+                "The new contents of the whole file.",
+            ),
+        ]
     }
 
-    async fn run(
-        &self,
-        args: HashMap<String, String>,
-        _settings: ExecuteCommandSettings,
-    ) -> String {
-        let params = ToolParams::new(args);
-        let file_path = match params.get_string(ParamName::FilePath.as_str()) {
-            Ok(s) => s,
-            Err(e) => return e,
-        };
-        let content = match params.get_string(ParamName::Content.as_str()) {
-            Ok(s) => s,
-            Err(e) => return e,
-        };
+    async fn run(&self, params: ToolParams) -> Result<ToolOutput, MinerveError> {
+        let file_path = params.get_string(ParamName::FilePath.as_str())?;
+        let content = params.get_string(ParamName::Content.as_str())?;
 
-        match std::fs::write(&file_path, content) {
-            Ok(_) => format!("Successfully set contents of file: {}", file_path),
-            Err(e) => format!("[Error] Failed to write to file {}: {}", file_path, e),
-        }
+        std::fs::write(&file_path, content)
+            .map_err(|e| MinerveError::io(format!("Failed to write to file {}", file_path), e))?;
+
+        Ok(
+            ToolOutput::success(format!("Successfully set contents of file: {}", file_path))
+                .touching(file_path),
+        )
     }
 }
@@ -1,6 +1,6 @@
-use crate::tools::{ExecuteCommandSettings, ParamName, Tool, ToolParams};
+use crate::error::MinerveError;
+use crate::tools::{Param, ParamName, ParamType, Tool, ToolOutput, ToolParams};
 use async_trait::async_trait;
-use std::collections::HashMap;
 use std::fs;
 
 pub struct ShowFileTool;
@@ -15,33 +15,31 @@ impl Tool for ShowFileTool {
         "Shows the content of a file. Prefer extract_structure when you just need an overview."
     }
 
-    fn parameters(&self) -> HashMap<&'static str, &'static str> {
-        let mut params = HashMap::new();
-        params.insert(ParamName::FilePath.as_str(), "string");
-        params
+    fn parameters(&self) -> Vec<Param> {
+        vec![Param::new(
+            ParamName::FilePath.as_str(),
+            ParamType::String,
+            "Path of the file, relative to the current directory.",
+        )]
     }
 
-    async fn run(
-        &self,
-        args: HashMap<String, String>,
-        _settings: ExecuteCommandSettings,
-    ) -> String {
-        let params = ToolParams::new(args);
-        let path = match params.get_string(ParamName::FilePath.as_str()) {
-            Ok(s) => s,
-            Err(e) => return e,
-        };
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    async fn run(&self, params: ToolParams) -> Result<ToolOutput, MinerveError> {
+        let path = params.get_string(ParamName::FilePath.as_str())?;
 
         match fs::read_to_string(&path) {
-            Ok(content) => content,
+            Ok(content) => Ok(ToolOutput::success(content)),
             Err(e) => {
                 let error_message = e.to_string();
                 if e.kind() == std::io::ErrorKind::NotFound
                     || error_message.contains("No such file or directory")
                 {
-                    "[file does not exist]".to_string()
+                    Ok(ToolOutput::error(format!("File does not exist: {}", path)))
                 } else {
-                    format!("[Error] Failed to read file: {}", e)
+                    Err(MinerveError::io("Failed to read file", e))
                 }
             }
         }
@@ -1,7 +1,66 @@
+use std::io;
+use std::process::{Output, Stdio};
+use tokio::process::{Child, Command};
+
 pub fn truncate(s: String, limit: usize) -> String {
     if s.len() > limit {
-        format!("{}\n...[truncated]", &s[..limit])
+        // Cut on a character boundary, slicing inside one would panic
+        let mut end = limit;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        format!("{}\n...[truncated]", &s[..end])
     } else {
         s
     }
 }
+
+/// Kills the process group of a child when dropped, so that the processes it
+/// started, e.g. the members of a `sh -c` pipeline, do not outlive it.
+pub struct ProcessGroup {
+    pgid: Option<libc::pid_t>,
+}
+
+impl ProcessGroup {
+    /// Leaves the group alone once the child exited on its own.
+    pub fn disarm(mut self) {
+        self.pgid = None;
+    }
+}
+
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        if let Some(pgid) = self.pgid {
+            // SAFETY: killpg has no memory-safety preconditions
+            unsafe {
+                libc::killpg(pgid, libc::SIGKILL);
+            }
+        }
+    }
+}
+
+/// Spawns `command` as the leader of a new process group, killed with all its
+/// descendants when the returned guard is dropped.
+pub fn spawn_in_group(command: &mut Command) -> io::Result<(Child, ProcessGroup)> {
+    let child = command.process_group(0).kill_on_drop(true).spawn()?;
+    let group = ProcessGroup {
+        pgid: child.id().map(|id| id as libc::pid_t),
+    };
+    Ok((child, group))
+}
+
+/// Runs a child process that gets killed, with the processes it started, if
+/// the calling task is aborted, e.g. when the user cancels the current request.
+pub async fn run_process(program: &str, args: &[&str]) -> io::Result<Output> {
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let (child, group) = spawn_in_group(&mut command)?;
+
+    let out = child.wait_with_output().await?;
+    group.disarm();
+    Ok(out)
+}
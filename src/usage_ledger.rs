@@ -0,0 +1,128 @@
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::USAGE_LEDGER_PATH;
+
+/// One API call, as appended to `~/.minerve/usage.jsonl`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageEntry {
+    pub timestamp: DateTime<Local>,
+    pub model: String,
+    pub project: String,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost_usd: f64,
+}
+
+pub struct UsageLedger {
+    path: PathBuf,
+}
+
+impl UsageLedger {
+    pub fn open_default() -> Option<Self> {
+        let path = dirs::home_dir()?.join(USAGE_LEDGER_PATH);
+        Some(Self { path })
+    }
+
+    pub fn append(&self, entry: &UsageEntry) {
+        let Ok(line) = serde_json::to_string(entry) else {
+            return;
+        };
+        if let Ok(mut file) = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+        {
+            let _ = writeln!(file, "{}", line);
+        }
+    }
+
+    /// Reads every entry, skipping lines that cannot be parsed.
+    pub fn entries(&self) -> Vec<UsageEntry> {
+        std::fs::read_to_string(&self.path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+
+    pub fn cost_on(&self, day: NaiveDate) -> f64 {
+        self.entries()
+            .iter()
+            .filter(|entry| entry.timestamp.date_naive() == day)
+            .map(|entry| entry.cost_usd)
+            .sum()
+    }
+}
+
+#[derive(Default)]
+struct UsageTotals {
+    calls: u64,
+    prompt_tokens: u64,
+    completion_tokens: u64,
+    cost_usd: f64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, entry: &UsageEntry) {
+        self.calls += 1;
+        self.prompt_tokens += entry.prompt_tokens;
+        self.completion_tokens += entry.completion_tokens;
+        self.cost_usd += entry.cost_usd;
+    }
+}
+
+fn format_section(title: &str, totals: &BTreeMap<String, UsageTotals>) -> String {
+    let mut output = format!("{}\n", title);
+    for (key, totals) in totals {
+        output.push_str(&format!(
+            "  {:<40} {:>6} calls {:>12} sent {:>10} received {:>10}\n",
+            key,
+            totals.calls,
+            totals.prompt_tokens,
+            totals.completion_tokens,
+            format!("${:.4}", totals.cost_usd)
+        ));
+    }
+    output
+}
+
+/// Renders totals by day, model and project for `minerve usage`.
+pub fn usage_report(entries: &[UsageEntry]) -> String {
+    if entries.is_empty() {
+        return String::from("No usage recorded yet.");
+    }
+
+    let mut by_day: BTreeMap<String, UsageTotals> = BTreeMap::new();
+    let mut by_model: BTreeMap<String, UsageTotals> = BTreeMap::new();
+    let mut by_project: BTreeMap<String, UsageTotals> = BTreeMap::new();
+    let mut total = UsageTotals::default();
+
+    for entry in entries {
+        by_day
+            .entry(entry.timestamp.date_naive().to_string())
+            .or_default()
+            .add(entry);
+        by_model.entry(entry.model.clone()).or_default().add(entry);
+        by_project
+            .entry(entry.project.clone())
+            .or_default()
+            .add(entry);
+        total.add(entry);
+    }
+
+    let mut totals = BTreeMap::new();
+    totals.insert(String::from("all"), total);
+
+    [
+        format_section("By day:", &by_day),
+        format_section("By model:", &by_model),
+        format_section("By project:", &by_project),
+        format_section("Total:", &totals),
+    ]
+    .join("\n")
+}
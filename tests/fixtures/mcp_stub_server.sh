@@ -0,0 +1,40 @@
+#!/bin/sh
+# Minimal MCP server over stdio for the client tests.
+#
+# Lists its tools on two pages. `echo` answers with its text, `fail` answers
+# with `isError`, and `exit` makes the server exit without answering. Started
+# with `broken`, it writes to stderr and exits before the handshake.
+
+if [ "$1" = "broken" ]; then
+    echo "stub: missing STUB_TOKEN" >&2
+    exit 1
+fi
+
+respond() {
+    printf '{"jsonrpc":"2.0","id":%s,"result":%s}\n' "$1" "$2"
+}
+
+while IFS= read -r line; do
+    id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9][0-9]*\).*/\1/p')
+    case "$line" in
+        *'"method":"initialize"'*)
+            echo "not a JSON-RPC message"
+            respond "$id" '{"protocolVersion":"2025-03-26","capabilities":{"tools":{}},"serverInfo":{"name":"stub","version":"1"}}'
+            ;;
+        *'"method":"tools/list"'*'"cursor"'*)
+            respond "$id" '{"tools":[{"name":"exit","description":"Exits"}]}'
+            ;;
+        *'"method":"tools/list"'*)
+            respond "$id" '{"tools":[{"name":"echo","description":"Echoes","annotations":{"readOnlyHint":true}},{"name":"fail","description":"Fails"}],"nextCursor":"2"}'
+            ;;
+        *'"method":"tools/call"'*'"name":"echo"'*)
+            respond "$id" '{"content":[{"type":"text","text":"hello"}]}'
+            ;;
+        *'"method":"tools/call"'*'"name":"fail"'*)
+            respond "$id" '{"content":[{"type":"text","text":"it failed"}],"isError":true}'
+            ;;
+        *'"method":"tools/call"'*'"name":"exit"'*)
+            exit 0
+            ;;
+    esac
+done